@@ -0,0 +1,693 @@
+use chrono::{NaiveTime, Timelike};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::retry::RetryPolicy;
+
+/// Depth limits accepted by the futures depth snapshot endpoint.
+const SNAPSHOT_DEPTHS: [u16; 7] = [5, 10, 20, 50, 100, 500, 1000];
+
+/// Largest `limit` accepted by the spot depth snapshot endpoint.
+const SPOT_MAX_SNAPSHOT_DEPTH: u16 = 5000;
+
+/// Which Binance market the pipeline talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Market {
+    /// USD-M futures (`fstream.binance.com`, `/fapi/v1/depth`).
+    #[default]
+    UsdMFutures,
+    /// COIN-M delivery futures (`dstream.binance.com`, `/dapi/v1/depth`),
+    /// e.g. `BTCUSD_PERP` or quarterlies like `BTCUSD_250926`.
+    CoinMFutures,
+    /// Spot (`stream.binance.com`, `/api/v3/depth`).
+    Spot,
+}
+
+impl Market {
+    pub fn default_ws_base_url(self) -> &'static str {
+        match self {
+            Market::UsdMFutures => "wss://fstream.binance.com",
+            Market::CoinMFutures => "wss://dstream.binance.com",
+            Market::Spot => "wss://stream.binance.com:9443",
+        }
+    }
+
+    pub fn default_rest_base_url(self) -> &'static str {
+        match self {
+            Market::UsdMFutures => "https://fapi.binance.com",
+            Market::CoinMFutures => "https://dapi.binance.com",
+            Market::Spot => "https://api.binance.com",
+        }
+    }
+
+    /// REST path of the depth snapshot endpoint.
+    pub fn depth_path(self) -> &'static str {
+        match self {
+            Market::UsdMFutures => "/fapi/v1/depth",
+            Market::CoinMFutures => "/dapi/v1/depth",
+            Market::Spot => "/api/v3/depth",
+        }
+    }
+
+    /// REST path of the `exchangeInfo` endpoint.
+    pub fn exchange_info_path(self) -> &'static str {
+        match self {
+            Market::UsdMFutures => "/fapi/v1/exchangeInfo",
+            Market::CoinMFutures => "/dapi/v1/exchangeInfo",
+            Market::Spot => "/api/v3/exchangeInfo",
+        }
+    }
+
+    /// Request weight Binance allows per IP per minute.
+    pub fn weight_limit(self) -> u32 {
+        match self {
+            Market::UsdMFutures | Market::CoinMFutures => 2400,
+            Market::Spot => 6000,
+        }
+    }
+
+    /// Request weight of one `exchangeInfo` request.
+    pub fn exchange_info_weight(self) -> u32 {
+        match self {
+            Market::UsdMFutures | Market::CoinMFutures => 1,
+            Market::Spot => 20,
+        }
+    }
+
+    /// Request weight of one depth snapshot with `limit` levels.
+    pub fn depth_weight(self, limit: u16) -> u32 {
+        match (self, limit) {
+            (Market::Spot, 0..=100) => 5,
+            (Market::Spot, 101..=500) => 25,
+            (Market::Spot, 501..=1000) => 50,
+            (Market::Spot, _) => 250,
+            (_, 0..=50) => 2,
+            (_, 51..=100) => 5,
+            (_, 101..=500) => 10,
+            (_, _) => 20,
+        }
+    }
+
+    fn valid_depth_stream(self, stream: DepthStream) -> bool {
+        let speed_ok = match (self, stream.speed_ms()) {
+            (_, None) => true,
+            (Market::Spot, Some(ms)) => ms == 100,
+            (Market::UsdMFutures | Market::CoinMFutures, Some(ms)) => {
+                matches!(ms, 100 | 250 | 500)
+            }
+        };
+        let levels_ok = match stream {
+            DepthStream::Diff { .. } => true,
+            DepthStream::Partial { levels, .. } => matches!(levels, 5 | 10 | 20),
+        };
+        speed_ok && levels_ok
+    }
+
+    fn valid_snapshot_depth(self, depth: u16) -> bool {
+        match self {
+            Market::UsdMFutures | Market::CoinMFutures => SNAPSHOT_DEPTHS.contains(&depth),
+            Market::Spot => (1..=SPOT_MAX_SNAPSHOT_DEPTH).contains(&depth),
+        }
+    }
+}
+
+/// Which depth stream a symbol is subscribed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthStream {
+    /// Incremental diff stream `@depth[@<ms>ms]`, bootstrapped from a REST
+    /// snapshot. `None` uses the exchange's default speed.
+    Diff { speed_ms: Option<u16> },
+    /// Top-N book stream `@depth<levels>[@<ms>ms]` (5, 10 or 20 levels).
+    /// Each message replaces the book; no REST snapshot or continuity checks.
+    Partial { levels: u8, speed_ms: Option<u16> },
+}
+
+impl DepthStream {
+    pub fn speed_ms(self) -> Option<u16> {
+        match self {
+            DepthStream::Diff { speed_ms } | DepthStream::Partial { speed_ms, .. } => speed_ms,
+        }
+    }
+
+    pub fn is_partial(self) -> bool {
+        matches!(self, DepthStream::Partial { .. })
+    }
+
+    /// Most levels a side can hold: the stream's top-N, or the REST
+    /// `snapshot_depth` a diff stream is bootstrapped from.
+    pub fn max_levels(self, snapshot_depth: u16) -> usize {
+        match self {
+            DepthStream::Diff { .. } => snapshot_depth.into(),
+            DepthStream::Partial { levels, .. } => levels.into(),
+        }
+    }
+
+    /// Stream suffix appended to the lower-case symbol, e.g. `@depth20@100ms`.
+    pub fn spec(self) -> String {
+        let base = match self {
+            DepthStream::Diff { .. } => "@depth".to_string(),
+            DepthStream::Partial { levels, .. } => format!("@depth{levels}"),
+        };
+        match self.speed_ms() {
+            Some(ms) => format!("{base}@{ms}ms"),
+            None => base,
+        }
+    }
+}
+
+impl Default for DepthStream {
+    fn default() -> Self {
+        DepthStream::Diff {
+            speed_ms: Some(100),
+        }
+    }
+}
+
+/// Validated configuration for the whole pipeline (router, WS streams and books).
+///
+/// Build it with [`StreamConfig::builder`]; every field has a sensible default
+/// for the selected [`Market`] (USD-M futures unless set) except the initial
+/// list of currency pairs, which
+/// may be empty if symbols are only added later through the [`StreamHandle`].
+///
+/// [`StreamHandle`]: crate::StreamHandle
+///
+/// ```
+/// use binance_stream_handler::{DepthStream, StreamConfig};
+/// use chrono::NaiveTime;
+///
+/// let cfg = StreamConfig::builder(["ADAUSDT", "DOGEUSDT"])
+///     .switch_cutoffs(
+///         NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+///         NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+///     )
+///     .ws_base_url("wss://fstream.binancefuture.com")
+///     .rest_base_url("https://testnet.binancefuture.com")
+///     .symbol_depth_stream("DOGEUSDT", DepthStream::Partial { levels: 20, speed_ms: Some(100) })
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(cfg.snapshot_depth(), 1000);
+/// assert_eq!(cfg.depth_stream_for("dogeusdt").spec(), "@depth20@100ms");
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    market: Market,
+    currency_pairs: Vec<String>,
+    chan_cap: usize,
+    park_cap: usize,
+    event_cap: usize,
+    resync_buffer_cap: usize,
+    switch_cutoffs: (NaiveTime, NaiveTime),
+    ws_base_url: String,
+    rest_base_url: String,
+    depth_stream: DepthStream,
+    symbol_streams: HashMap<String, DepthStream>,
+    snapshot_depth: u16,
+    overlap: Duration,
+    tick_ladder: bool,
+    stale_after: Duration,
+    min_book_levels: usize,
+    snapshot_retry: RetryPolicy,
+    ws_reconnect: RetryPolicy,
+    rest_weight_budget: u32,
+}
+
+impl StreamConfig {
+    pub fn builder<I, S>(currency_pairs: I) -> StreamConfigBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        StreamConfigBuilder::new(currency_pairs)
+    }
+
+    pub fn market(&self) -> Market {
+        self.market
+    }
+
+    /// Upper-cased symbols, e.g. `"ADAUSDT"`.
+    pub fn currency_pairs(&self) -> &[String] {
+        &self.currency_pairs
+    }
+
+    /// Capacity of each per-symbol router → book channel.
+    pub fn chan_cap(&self) -> usize {
+        self.chan_cap
+    }
+
+    /// Per-symbol bound of the buffer used while both connections are open.
+    pub fn park_cap(&self) -> usize {
+        self.park_cap
+    }
+
+    /// Capacity of each per-symbol [`BookEvent`] broadcast channel.
+    ///
+    /// [`BookEvent`]: crate::BookEvent
+    pub fn event_cap(&self) -> usize {
+        self.event_cap
+    }
+
+    /// Most updates a book buffers while a REST snapshot is in flight.
+    pub fn resync_buffer_cap(&self) -> usize {
+        self.resync_buffer_cap
+    }
+
+    /// UTC times of day at which connection A and B hand over to each other.
+    pub fn switch_cutoffs(&self) -> (NaiveTime, NaiveTime) {
+        self.switch_cutoffs
+    }
+
+    /// WebSocket base, e.g. `wss://fstream.binance.com`. Defaults per [`Market`].
+    pub fn ws_base_url(&self) -> &str {
+        &self.ws_base_url
+    }
+
+    /// REST base, e.g. `https://fapi.binance.com`. Defaults per [`Market`].
+    pub fn rest_base_url(&self) -> &str {
+        &self.rest_base_url
+    }
+
+    /// Default depth stream for symbols without an override.
+    pub fn depth_stream(&self) -> DepthStream {
+        self.depth_stream
+    }
+
+    /// Depth stream configured for `symbol`, falling back to [`Self::depth_stream`].
+    pub fn depth_stream_for(&self, symbol: &str) -> DepthStream {
+        self.symbol_streams
+            .get(&symbol.to_ascii_uppercase())
+            .copied()
+            .unwrap_or(self.depth_stream)
+    }
+
+    /// Whether `stream` is available on this config's market.
+    pub fn supports_depth_stream(&self, stream: DepthStream) -> bool {
+        self.market.valid_depth_stream(stream)
+    }
+
+    /// Whether books on `stream` can hold [`min_book_levels`](Self::min_book_levels)
+    /// a side, i.e. can ever pass the integrity check.
+    pub fn fits_min_book_levels(&self, stream: DepthStream) -> bool {
+        stream.max_levels(self.snapshot_depth) >= self.min_book_levels
+    }
+
+    /// `limit` passed to the REST depth snapshot.
+    pub fn snapshot_depth(&self) -> u16 {
+        self.snapshot_depth
+    }
+
+    /// How long both connections run side by side before each cutoff.
+    pub fn overlap(&self) -> Duration {
+        self.overlap
+    }
+
+    /// Whether symbols are checked against `exchangeInfo` at startup and
+    /// books keep integer tick/lot ladders.
+    pub fn tick_ladder(&self) -> bool {
+        self.tick_ladder
+    }
+
+    /// Silence after which a live book is reported as `Stale`.
+    pub fn stale_after(&self) -> Duration {
+        self.stale_after
+    }
+
+    /// Fewest levels a side may hold before the book counts as collapsed.
+    pub fn min_book_levels(&self) -> usize {
+        self.min_book_levels
+    }
+
+    /// Retry policy for REST depth snapshots.
+    pub fn snapshot_retry(&self) -> &RetryPolicy {
+        &self.snapshot_retry
+    }
+
+    /// Backoff for reconnecting a dropped WebSocket connection.
+    pub fn ws_reconnect(&self) -> &RetryPolicy {
+        &self.ws_reconnect
+    }
+
+    /// Request weight per minute the snapshot client allows itself.
+    pub fn rest_weight_budget(&self) -> u32 {
+        self.rest_weight_budget
+    }
+}
+
+pub struct StreamConfigBuilder {
+    market: Market,
+    currency_pairs: Vec<String>,
+    chan_cap: usize,
+    park_cap: usize,
+    event_cap: usize,
+    resync_buffer_cap: usize,
+    switch_cutoffs: (NaiveTime, NaiveTime),
+    ws_base_url: Option<String>,
+    rest_base_url: Option<String>,
+    depth_stream: DepthStream,
+    symbol_streams: HashMap<String, DepthStream>,
+    snapshot_depth: u16,
+    overlap: Duration,
+    tick_ladder: bool,
+    stale_after: Duration,
+    min_book_levels: usize,
+    snapshot_retry: RetryPolicy,
+    ws_reconnect: RetryPolicy,
+    rest_weight_budget: Option<u32>,
+}
+
+impl StreamConfigBuilder {
+    fn new<I, S>(currency_pairs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            market: Market::default(),
+            currency_pairs: currency_pairs
+                .into_iter()
+                .map(|s| s.as_ref().to_ascii_uppercase())
+                .collect(),
+            chan_cap: 1024,
+            park_cap: 512,
+            event_cap: 1024,
+            resync_buffer_cap: 4096,
+            switch_cutoffs: (
+                NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            ),
+            ws_base_url: None,
+            rest_base_url: None,
+            depth_stream: DepthStream::default(),
+            symbol_streams: HashMap::new(),
+            snapshot_depth: 1000,
+            overlap: Duration::from_secs(3),
+            tick_ladder: false,
+            stale_after: Duration::from_secs(10),
+            min_book_levels: 1,
+            snapshot_retry: RetryPolicy::default(),
+            ws_reconnect: RetryPolicy::default(),
+            rest_weight_budget: None,
+        }
+    }
+
+    /// Selects the market; base URLs not set explicitly follow it.
+    pub fn market(mut self, market: Market) -> Self {
+        self.market = market;
+        self
+    }
+
+    pub fn chan_cap(mut self, chan_cap: usize) -> Self {
+        self.chan_cap = chan_cap;
+        self
+    }
+
+    pub fn park_cap(mut self, park_cap: usize) -> Self {
+        self.park_cap = park_cap;
+        self
+    }
+
+    /// How many book events a slow subscriber may fall behind before it lags.
+    pub fn event_cap(mut self, event_cap: usize) -> Self {
+        self.event_cap = event_cap;
+        self
+    }
+
+    /// How many updates a book holds back while it fetches a snapshot
+    /// (default 4096). On overflow the oldest are dropped, which costs one
+    /// more snapshot.
+    pub fn resync_buffer_cap(mut self, cap: usize) -> Self {
+        self.resync_buffer_cap = cap;
+        self
+    }
+
+    pub fn switch_cutoffs(mut self, cut_a: NaiveTime, cut_b: NaiveTime) -> Self {
+        self.switch_cutoffs = (cut_a, cut_b);
+        self
+    }
+
+    pub fn ws_base_url(mut self, url: impl Into<String>) -> Self {
+        self.ws_base_url = Some(url.into());
+        self
+    }
+
+    pub fn rest_base_url(mut self, url: impl Into<String>) -> Self {
+        self.rest_base_url = Some(url.into());
+        self
+    }
+
+    /// Depth stream used for every symbol without an override.
+    pub fn depth_stream(mut self, stream: DepthStream) -> Self {
+        self.depth_stream = stream;
+        self
+    }
+
+    /// Overrides the depth stream for one symbol, e.g. a cheap top-20 book.
+    pub fn symbol_depth_stream(mut self, symbol: &str, stream: DepthStream) -> Self {
+        self.symbol_streams
+            .insert(symbol.to_ascii_uppercase(), stream);
+        self
+    }
+
+    pub fn snapshot_depth(mut self, depth: u16) -> Self {
+        self.snapshot_depth = depth;
+        self
+    }
+
+    pub fn overlap(mut self, overlap: Duration) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Fetches `exchangeInfo` before connecting: unknown or non-trading
+    /// symbols fail startup, and every book also stores its levels as integer
+    /// tick and lot counts, rejecting off-grid updates.
+    pub fn tick_ladder(mut self, enabled: bool) -> Self {
+        self.tick_ladder = enabled;
+        self
+    }
+
+    /// How long a live book may go without updates before its feed status
+    /// turns `Stale` (default 10s). Quiet symbols may need more.
+    pub fn stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    /// Fewest levels each side must keep to pass the integrity check
+    /// (default 1, i.e. a side may not empty out); `0` disables the check.
+    /// [`build`](Self::build) rejects a value above the levels the configured
+    /// streams deliver.
+    pub fn min_book_levels(mut self, levels: usize) -> Self {
+        self.min_book_levels = levels;
+        self
+    }
+
+    /// How failed snapshot fetches are retried (default
+    /// [`RetryPolicy::default`]).
+    pub fn snapshot_retry(mut self, policy: RetryPolicy) -> Self {
+        self.snapshot_retry = policy;
+        self
+    }
+
+    /// How a dropped router connection is reconnected (default
+    /// [`RetryPolicy::default`]). With `max_attempts` set, a connection that
+    /// still fails stays closed until the next A/B mode change reopens it.
+    /// `on_escalate` is not called for reconnects; watch
+    /// [`StreamHandle::connection_events`](crate::StreamHandle::connection_events)
+    /// instead.
+    pub fn ws_reconnect(mut self, policy: RetryPolicy) -> Self {
+        self.ws_reconnect = policy;
+        self
+    }
+
+    /// Request weight per minute that snapshot fetches may use (default 80%
+    /// of [`Market::weight_limit`]). Lower it if other processes share the IP.
+    pub fn rest_weight_budget(mut self, weight: u32) -> Self {
+        self.rest_weight_budget = Some(weight);
+        self
+    }
+
+    pub fn build(self) -> Result<StreamConfig, ConfigError> {
+        for (i, sym) in self.currency_pairs.iter().enumerate() {
+            if !is_valid_symbol(sym) {
+                return Err(ConfigError::InvalidSymbol(sym.clone()));
+            }
+            if self.currency_pairs[..i].contains(sym) {
+                return Err(ConfigError::DuplicateSymbol(sym.clone()));
+            }
+        }
+        if self.chan_cap == 0 {
+            return Err(ConfigError::ZeroCapacity("chan_cap"));
+        }
+        if self.park_cap == 0 {
+            return Err(ConfigError::ZeroCapacity("park_cap"));
+        }
+        if self.event_cap == 0 {
+            return Err(ConfigError::ZeroCapacity("event_cap"));
+        }
+        if self.resync_buffer_cap == 0 {
+            return Err(ConfigError::ZeroCapacity("resync_buffer_cap"));
+        }
+
+        let ws_base_url = trim_base_url(
+            self.ws_base_url
+                .as_deref()
+                .unwrap_or(self.market.default_ws_base_url()),
+            &["ws://", "wss://"],
+        )?;
+        let rest_base_url = trim_base_url(
+            self.rest_base_url
+                .as_deref()
+                .unwrap_or(self.market.default_rest_base_url()),
+            &["http://", "https://"],
+        )?;
+
+        if !self.market.valid_depth_stream(self.depth_stream) {
+            return Err(ConfigError::InvalidDepthStream(self.market, self.depth_stream));
+        }
+        for (sym, stream) in &self.symbol_streams {
+            if !self.currency_pairs.contains(sym) {
+                return Err(ConfigError::UnknownSymbol(sym.clone()));
+            }
+            if !self.market.valid_depth_stream(*stream) {
+                return Err(ConfigError::InvalidDepthStream(self.market, *stream));
+            }
+        }
+        if !self.market.valid_snapshot_depth(self.snapshot_depth) {
+            return Err(ConfigError::InvalidSnapshotDepth(self.market, self.snapshot_depth));
+        }
+        for stream in std::iter::once(&self.depth_stream).chain(self.symbol_streams.values()) {
+            if stream.max_levels(self.snapshot_depth) < self.min_book_levels {
+                return Err(ConfigError::MinBookLevelsAboveDepth(self.min_book_levels, *stream));
+            }
+        }
+
+        // Both connections must each get a solo window longer than the overlap.
+        let (cut_a, cut_b) = self.switch_cutoffs;
+        const DAY: i64 = 24 * 60 * 60;
+        let a = cut_a.num_seconds_from_midnight() as i64;
+        let b = cut_b.num_seconds_from_midnight() as i64;
+        let span_a = (b - a).rem_euclid(DAY);
+        let span_b = DAY - span_a;
+        if span_a == 0 {
+            return Err(ConfigError::InvalidCutoffs(cut_a, cut_b));
+        }
+        let overlap_secs = self.overlap.as_secs() as i64;
+        if overlap_secs == 0 || overlap_secs >= span_a.min(span_b) {
+            return Err(ConfigError::InvalidOverlap(self.overlap));
+        }
+
+        if self.stale_after.is_zero() {
+            return Err(ConfigError::InvalidStaleAfter(self.stale_after));
+        }
+        self.snapshot_retry.validate("snapshot_retry")?;
+        self.ws_reconnect.validate("ws_reconnect")?;
+        let rest_weight_budget = self
+            .rest_weight_budget
+            .unwrap_or(self.market.weight_limit() / 5 * 4);
+        if rest_weight_budget == 0 {
+            return Err(ConfigError::ZeroCapacity("rest_weight_budget"));
+        }
+
+        Ok(StreamConfig {
+            market: self.market,
+            currency_pairs: self.currency_pairs,
+            chan_cap: self.chan_cap,
+            park_cap: self.park_cap,
+            event_cap: self.event_cap,
+            resync_buffer_cap: self.resync_buffer_cap,
+            switch_cutoffs: self.switch_cutoffs,
+            ws_base_url,
+            rest_base_url,
+            depth_stream: self.depth_stream,
+            symbol_streams: self.symbol_streams,
+            snapshot_depth: self.snapshot_depth,
+            overlap: self.overlap,
+            tick_ladder: self.tick_ladder,
+            stale_after: self.stale_after,
+            min_book_levels: self.min_book_levels,
+            snapshot_retry: self.snapshot_retry,
+            ws_reconnect: self.ws_reconnect,
+            rest_weight_budget,
+        })
+    }
+}
+
+/// Binance symbols are upper-case alphanumerics, with `_` for delivery contracts.
+pub(crate) fn is_valid_symbol(sym: &str) -> bool {
+    !sym.is_empty() && sym.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn trim_base_url(url: &str, schemes: &[&str]) -> Result<String, ConfigError> {
+    let trimmed = url.trim_end_matches('/');
+    match schemes.iter().find(|s| trimmed.starts_with(*s)) {
+        Some(scheme) if trimmed.len() > scheme.len() => Ok(trimmed.to_string()),
+        _ => Err(ConfigError::InvalidUrl(url.to_string())),
+    }
+}
+
+/// Why a [`StreamConfigBuilder`] was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    InvalidSymbol(String),
+    DuplicateSymbol(String),
+    ZeroCapacity(&'static str),
+    InvalidUrl(String),
+    UnknownSymbol(String),
+    InvalidDepthStream(Market, DepthStream),
+    InvalidSnapshotDepth(Market, u16),
+    InvalidCutoffs(NaiveTime, NaiveTime),
+    InvalidOverlap(Duration),
+    InvalidStaleAfter(Duration),
+    /// `policy` is the builder field (`snapshot_retry` or `ws_reconnect`).
+    InvalidRetryPolicy {
+        policy: &'static str,
+        reason: &'static str,
+    },
+    /// `min_book_levels` is more than the stream (or snapshot) delivers, so
+    /// every book would fail the integrity check.
+    MinBookLevelsAboveDepth(usize, DepthStream),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidSymbol(s) => write!(f, "invalid symbol {s:?}"),
+            ConfigError::DuplicateSymbol(s) => write!(f, "symbol {s} is listed twice"),
+            ConfigError::ZeroCapacity(name) => write!(f, "{name} must be greater than zero"),
+            ConfigError::InvalidUrl(u) => write!(f, "invalid base url {u:?}"),
+            ConfigError::UnknownSymbol(s) => {
+                write!(f, "stream override for {s}, which is not in currency_pairs")
+            }
+            ConfigError::InvalidDepthStream(market, stream) => {
+                write!(f, "{} is not available on {market:?}", stream.spec())
+            }
+            ConfigError::InvalidSnapshotDepth(Market::Spot, d) => {
+                write!(f, "spot snapshot depth {d} must be 1..={SPOT_MAX_SNAPSHOT_DEPTH}")
+            }
+            ConfigError::InvalidSnapshotDepth(market, d) => {
+                write!(f, "{market:?} snapshot depth {d} is not one of {SNAPSHOT_DEPTHS:?}")
+            }
+            ConfigError::InvalidCutoffs(a, b) => {
+                write!(f, "switch cutoffs {a} and {b} must differ")
+            }
+            ConfigError::InvalidOverlap(d) => write!(
+                f,
+                "overlap {d:?} must be at least 1s and shorter than both connection windows"
+            ),
+            ConfigError::InvalidStaleAfter(d) => {
+                write!(f, "stale_after {d:?} must be greater than zero")
+            }
+            ConfigError::InvalidRetryPolicy { policy, reason } => write!(f, "{policy}: {reason}"),
+            ConfigError::MinBookLevelsAboveDepth(min, stream) => write!(
+                f,
+                "min_book_levels {min} exceeds the levels a side {} books receive",
+                stream.spec()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
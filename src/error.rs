@@ -0,0 +1,312 @@
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite;
+
+use crate::config::ConfigError;
+use crate::ob_manager::integrity::IntegrityViolation;
+
+/// Errors returned by the snapshot, stream and book APIs.
+///
+/// Every variant is `Send + Sync`, so it can cross task boundaries and be
+/// matched on by retry policies.
+#[derive(Debug)]
+pub enum Error {
+    /// The exchange answered 429 (rate limited) or 418 (IP banned).
+    RateLimited {
+        symbol: Option<String>,
+        status: u16,
+        code: Option<i64>,
+        retry_after: Option<Duration>,
+    },
+    /// Any other non-success HTTP status, with Binance's error code if present.
+    Http {
+        symbol: Option<String>,
+        status: u16,
+        code: Option<i64>,
+        message: Option<String>,
+    },
+    /// DNS, connect, TLS or timeout failure before a response arrived.
+    Transport {
+        symbol: Option<String>,
+        source: reqwest::Error,
+    },
+    /// The response body was not the JSON we expected.
+    Decode {
+        symbol: Option<String>,
+        source: serde_json::Error,
+    },
+    /// The WebSocket connection could not be established. `status` is set
+    /// when the server answered the upgrade request with an HTTP error.
+    WsHandshake {
+        status: Option<u16>,
+        source: Box<tungstenite::Error>,
+    },
+    Config(ConfigError),
+    /// Reading a local file failed, e.g. a recorded snapshot.
+    Io {
+        symbol: Option<String>,
+        source: std::io::Error,
+    },
+    /// `exchangeInfo` does not list the symbol, or lists it with a status
+    /// other than `TRADING` (e.g. `BREAK`, `SETTLING`, `DELIVERING`).
+    UnknownSymbol {
+        symbol: String,
+        status: Option<String>,
+    },
+    /// A snapshot failed the book integrity check, see
+    /// [`StreamConfig::min_book_levels`](crate::StreamConfig::min_book_levels).
+    Integrity {
+        symbol: String,
+        violation: IntegrityViolation,
+    },
+}
+
+/// Error payload Binance returns alongside non-2xx responses.
+#[derive(Debug, Deserialize)]
+struct BinanceErrorBody {
+    code: i64,
+    msg: String,
+}
+
+impl Error {
+    /// Builds the error for a non-success REST response from its status,
+    /// `Retry-After` header and body.
+    pub(crate) fn from_response(
+        symbol: Option<&str>,
+        status: reqwest::StatusCode,
+        retry_after: Option<Duration>,
+        body: &[u8],
+    ) -> Self {
+        let parsed = serde_json::from_slice::<BinanceErrorBody>(body).ok();
+        let symbol = symbol.map(str::to_string);
+        let status = status.as_u16();
+        match status {
+            418 | 429 => Error::RateLimited {
+                symbol,
+                status,
+                code: parsed.map(|b| b.code),
+                retry_after,
+            },
+            _ => Error::Http {
+                symbol,
+                status,
+                code: parsed.as_ref().map(|b| b.code),
+                message: parsed.map(|b| b.msg),
+            },
+        }
+    }
+
+    pub(crate) fn ws_handshake(source: tungstenite::Error) -> Self {
+        let status = match &source {
+            tungstenite::Error::Http(resp) => Some(resp.status().as_u16()),
+            _ => None,
+        };
+        Error::WsHandshake {
+            status,
+            source: Box::new(source),
+        }
+    }
+
+    /// Symbol the failing request was for, if any.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            Error::RateLimited { symbol, .. }
+            | Error::Http { symbol, .. }
+            | Error::Transport { symbol, .. }
+            | Error::Decode { symbol, .. }
+            | Error::Io { symbol, .. } => symbol.as_deref(),
+            Error::UnknownSymbol { symbol, .. } | Error::Integrity { symbol, .. } => Some(symbol),
+            Error::WsHandshake { .. } | Error::Config(_) => None,
+        }
+    }
+
+    /// HTTP status returned by the exchange, if a response was received.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::RateLimited { status, .. } | Error::Http { status, .. } => Some(*status),
+            Error::WsHandshake { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// Binance error code (e.g. `-1121` for an invalid symbol), if provided.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            Error::RateLimited { code, .. } | Error::Http { code, .. } => *code,
+            _ => None,
+        }
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Error::RateLimited { .. })
+    }
+
+    /// How long the exchange asked us to back off, from `Retry-After`.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether retrying the same request later could succeed.
+    ///
+    /// Rate limits, 5xx responses, transport failures, handshakes that got
+    /// no HTTP answer and inconsistent snapshots are considered transient;
+    /// 4xx answers (including handshake rejections), decode, config and file
+    /// errors, unknown symbols and snapshots off the tick grid are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Integrity {
+                violation: IntegrityViolation::OffGrid { .. },
+                ..
+            } => false,
+            Error::RateLimited { .. } | Error::Transport { .. } | Error::Integrity { .. } => true,
+            Error::WsHandshake { status, .. } => {
+                status.map_or(true, |s| matches!(s, 418 | 429) || s >= 500)
+            }
+            Error::Http { status, .. } => *status >= 500,
+            Error::Decode { .. }
+            | Error::Config(_)
+            | Error::UnknownSymbol { .. }
+            | Error::Io { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sym = self.symbol().unwrap_or("-");
+        match self {
+            Error::RateLimited {
+                status,
+                code,
+                retry_after,
+                ..
+            } => write!(
+                f,
+                "{sym}: rate limited (HTTP {status}, code {code:?}, retry after {retry_after:?})"
+            ),
+            Error::Http {
+                status,
+                code,
+                message,
+                ..
+            } => match (code, message) {
+                (Some(code), Some(msg)) => write!(f, "{sym}: HTTP {status}: {msg} (code {code})"),
+                _ => write!(f, "{sym}: HTTP {status}"),
+            },
+            Error::Transport { source, .. } => write!(f, "{sym}: request failed: {source}"),
+            Error::Decode { source, .. } => write!(f, "{sym}: invalid response body: {source}"),
+            Error::WsHandshake {
+                status: Some(status),
+                ..
+            } => write!(f, "WebSocket handshake rejected with HTTP {status}"),
+            Error::WsHandshake { source, .. } => write!(f, "WebSocket connect failed: {source}"),
+            Error::Config(e) => write!(f, "invalid config: {e}"),
+            Error::Io { source, .. } => write!(f, "{sym}: {source}"),
+            Error::UnknownSymbol {
+                status: Some(status),
+                ..
+            } => write!(f, "{sym}: not trading (status {status})"),
+            Error::UnknownSymbol { .. } => write!(f, "{sym}: unknown symbol"),
+            Error::Integrity { violation, .. } => write!(f, "{sym}: inconsistent snapshot: {violation}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport { source, .. } => Some(source),
+            Error::Decode { source, .. } => Some(source),
+            Error::WsHandshake { source, .. } => Some(source.as_ref()),
+            Error::Config(e) => Some(e),
+            Error::Io { source, .. } => Some(source),
+            Error::RateLimited { .. }
+            | Error::Http { .. }
+            | Error::UnknownSymbol { .. }
+            | Error::Integrity { .. } => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Error>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ob_manager::events::BookSide;
+    use reqwest::StatusCode;
+
+    fn rest(status: u16) -> Error {
+        let status = StatusCode::from_u16(status).unwrap();
+        Error::from_response(Some("BTCUSDT"), status, None, br#"{"code":-1000,"msg":"x"}"#)
+    }
+
+    fn handshake(status: Option<u16>) -> Error {
+        let source = match status {
+            Some(s) => {
+                let resp = tungstenite::http::Response::builder().status(s).body(None).unwrap();
+                tungstenite::Error::Http(resp)
+            }
+            None => tungstenite::Error::ConnectionClosed,
+        };
+        Error::ws_handshake(source)
+    }
+
+    #[test]
+    fn rest_statuses_are_classified() {
+        for status in [418, 429] {
+            assert!(rest(status).is_rate_limited(), "{status}");
+            assert!(rest(status).is_retryable(), "{status}");
+        }
+        for status in [500, 502, 503] {
+            assert!(rest(status).is_retryable(), "{status}");
+        }
+        for status in [400, 403, 404, 451] {
+            assert!(!rest(status).is_retryable(), "{status}");
+        }
+        assert_eq!(rest(400).code(), Some(-1000));
+    }
+
+    #[test]
+    fn handshake_retries_only_without_an_answer_or_on_throttling() {
+        assert!(handshake(None).is_retryable());
+        assert!(handshake(Some(429)).is_retryable());
+        assert!(handshake(Some(418)).is_retryable());
+        assert!(handshake(Some(503)).is_retryable());
+        assert!(!handshake(Some(451)).is_retryable());
+        assert!(!handshake(Some(403)).is_retryable());
+        assert_eq!(handshake(Some(451)).status(), Some(451));
+    }
+
+    #[test]
+    fn inconsistent_snapshots_retry_but_off_grid_ones_do_not() {
+        let integrity = |violation| Error::Integrity {
+            symbol: "BTCUSDT".into(),
+            violation,
+        };
+        let collapse = IntegrityViolation::DepthCollapse {
+            side: BookSide::Bid,
+            levels: 0,
+        };
+        let off_grid = IntegrityViolation::OffGrid {
+            side: BookSide::Ask,
+            price: Default::default(),
+            qty: Default::default(),
+        };
+        assert!(integrity(collapse).is_retryable());
+        assert!(!integrity(off_grid).is_retryable());
+    }
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::config::{is_valid_symbol, DepthStream, StreamConfig};
+use crate::error::Error;
+use crate::latency::{Connection, ConnectionLatency, LatencyStats, SymbolLatencyStats};
+use crate::ob_manager::events::{BboEvent, BookEvent};
+use crate::ob_manager::order_book::OrderBook;
+use crate::ob_manager::snapshot_client::SnapshotPriority;
+use crate::ob_manager::snapshot_provider::SnapshotProvider;
+use crate::ob_manager::status::FeedHealth;
+use crate::ob_manager::{spawn_order_book, BookOutputs};
+use crate::router::{ConnectionEvent, RouterCommand, RouterHandle, RouterMessage};
+
+/// Control handle for a running pipeline, returned by [`generate_orderbooks`].
+///
+/// Cloning is cheap; all clones control the same router and share the same
+/// set of published books.
+///
+/// [`generate_orderbooks`]: crate::generate_orderbooks
+#[derive(Clone)]
+pub struct StreamHandle {
+    config: Arc<StreamConfig>,
+    books: Arc<Mutex<HashMap<String, BookOutputs>>>,
+    book_tasks: Arc<Mutex<HashMap<String, JoinHandle<()>>>>,
+    router_tx: mpsc::Sender<RouterCommand>,
+    conn_latency: Arc<ConnectionLatency>,
+    conn_events: broadcast::Sender<ConnectionEvent>,
+    snapshots: Arc<dyn SnapshotProvider>,
+    // Taken by the first `shutdown` call.
+    router: Arc<tokio::sync::Mutex<Option<RouterHandle>>>,
+    // Serialises add/remove so two callers can't race on the same symbol.
+    control_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl StreamHandle {
+    pub(crate) fn new(
+        config: Arc<StreamConfig>,
+        books: HashMap<String, BookOutputs>,
+        book_tasks: HashMap<String, JoinHandle<()>>,
+        snapshots: Arc<dyn SnapshotProvider>,
+        router: RouterHandle,
+    ) -> Self {
+        Self {
+            config,
+            books: Arc::new(Mutex::new(books)),
+            book_tasks: Arc::new(Mutex::new(book_tasks)),
+            router_tx: router.commands.clone(),
+            conn_latency: router.latency.clone(),
+            conn_events: router.events.clone(),
+            snapshots,
+            router: Arc::new(tokio::sync::Mutex::new(Some(router))),
+            control_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Book receiver for `symbol` (case-insensitive), if it is subscribed.
+    pub fn order_book(&self, symbol: &str) -> Option<watch::Receiver<OrderBook>> {
+        let sym = symbol.to_ascii_uppercase();
+        self.books.lock().unwrap().get(&sym).map(|o| o.book.clone())
+    }
+
+    /// All currently subscribed books, keyed by upper-case symbol.
+    pub fn order_books(&self) -> HashMap<String, watch::Receiver<OrderBook>> {
+        self.books
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(sym, o)| (sym.clone(), o.book.clone()))
+            .collect()
+    }
+
+    /// Every change to `symbol`'s book, if it is subscribed.
+    ///
+    /// Unlike the `watch` channel nothing is conflated: each applied update
+    /// arrives as a [`BookEvent::Delta`] and each rebuild (resync, partial
+    /// message) as a [`BookEvent::Reset`]. A receiver that falls more than
+    /// [`StreamConfig::event_cap`] events behind gets `Lagged`; it can then
+    /// start over from [`order_book`](Self::order_book) and skip deltas with
+    /// `final_update_id <= last_u`.
+    pub fn book_events(&self, symbol: &str) -> Option<broadcast::Receiver<BookEvent>> {
+        let sym = symbol.to_ascii_uppercase();
+        self.books
+            .lock()
+            .unwrap()
+            .get(&sym)
+            .map(|o| o.events.subscribe())
+    }
+
+    /// Feed health of `symbol`, if it is subscribed.
+    ///
+    /// Gate trading on [`FeedHealth::is_live`]: while a resync is pending or
+    /// the feed has gone quiet, the book in [`order_book`](Self::order_book)
+    /// is out of date even though it still holds levels.
+    pub fn feed_status(&self, symbol: &str) -> Option<watch::Receiver<FeedHealth>> {
+        let sym = symbol.to_ascii_uppercase();
+        self.books
+            .lock()
+            .unwrap()
+            .get(&sym)
+            .map(|o| o.status.clone())
+    }
+
+    /// Rolling latency histograms of `symbol`, if it is subscribed:
+    /// exchange → socket (`E` vs local wall clock), socket → book, and `T` → `E`.
+    pub fn latency(&self, symbol: &str) -> Option<SymbolLatencyStats> {
+        let sym = symbol.to_ascii_uppercase();
+        let latency = self.books.lock().unwrap().get(&sym)?.latency.clone();
+        let stats = latency.lock().unwrap().stats();
+        Some(stats)
+    }
+
+    /// Rolling exchange → socket latency of one router connection, over all
+    /// symbols it delivered. Compare A and B during the overlap window.
+    pub fn connection_latency(&self, conn: Connection) -> LatencyStats {
+        self.conn_latency.stats(conn)
+    }
+
+    /// Disconnects and reconnects of the router's A and B connections.
+    ///
+    /// A dropped connection is reopened per [`StreamConfig::ws_reconnect`]
+    /// and resubscribed to every stream; the symbols it was delivering go
+    /// `Resyncing` and take a fresh snapshot once updates resume.
+    pub fn connection_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.conn_events.subscribe()
+    }
+
+    /// Top-of-book changes of `symbol`, if it is subscribed.
+    ///
+    /// Emits only when the best bid or ask price or quantity changes, so a
+    /// strategy that only needs the touch is not woken by deeper levels.
+    pub fn bbo_events(&self, symbol: &str) -> Option<broadcast::Receiver<BboEvent>> {
+        let sym = symbol.to_ascii_uppercase();
+        self.books
+            .lock()
+            .unwrap()
+            .get(&sym)
+            .map(|o| o.bbo.subscribe())
+    }
+
+    /// Currently subscribed symbols.
+    pub fn symbols(&self) -> Vec<String> {
+        self.books.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Subscribes `symbol` on the live sockets and starts its order book task.
+    ///
+    /// The symbol is subscribed before the REST snapshot is requested so that
+    /// no bridging update is missed. Uses the config's depth stream for it.
+    pub async fn add_symbol(
+        &self,
+        symbol: &str,
+    ) -> Result<watch::Receiver<OrderBook>, SubscriptionError> {
+        let stream = self.config.depth_stream_for(symbol);
+        self.add_symbol_with_stream(symbol, stream).await
+    }
+
+    /// Like [`add_symbol`](Self::add_symbol), with an explicit depth stream,
+    /// e.g. a partial top-20 book for a long-tail symbol.
+    pub async fn add_symbol_with_stream(
+        &self,
+        symbol: &str,
+        stream: DepthStream,
+    ) -> Result<watch::Receiver<OrderBook>, SubscriptionError> {
+        let sym = symbol.to_ascii_uppercase();
+        if !is_valid_symbol(&sym) {
+            return Err(SubscriptionError::InvalidSymbol(sym));
+        }
+        if !self.config.supports_depth_stream(stream) {
+            return Err(SubscriptionError::UnsupportedStream(stream));
+        }
+        if !self.config.fits_min_book_levels(stream) {
+            return Err(SubscriptionError::TooShallow(stream));
+        }
+
+        let _guard = self.control_lock.lock().await;
+        if self.books.lock().unwrap().contains_key(&sym) {
+            return Err(SubscriptionError::AlreadySubscribed(sym));
+        }
+
+        let filters = if self.config.tick_ladder() {
+            let mut filters = self
+                .snapshots
+                .symbol_filters(&self.config, std::slice::from_ref(&sym), SnapshotPriority::Initial)
+                .await
+                .map_err(SubscriptionError::ExchangeInfo)?;
+            filters.remove(&sym)
+        } else {
+            None
+        };
+
+        let (tx, rx) = mpsc::channel::<RouterMessage>(self.config.chan_cap());
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.router_tx
+            .send(RouterCommand::Subscribe {
+                symbol: sym.clone(),
+                stream,
+                tx,
+                ack: ack_tx,
+            })
+            .await
+            .map_err(|_| SubscriptionError::RouterClosed)?;
+        ack_rx.await.map_err(|_| SubscriptionError::RouterClosed)?;
+
+        let (outputs, task) = spawn_order_book(
+            sym.clone(),
+            self.config.clone(),
+            stream,
+            filters,
+            self.snapshots.clone(),
+            rx,
+        );
+        let rx_ob = outputs.book.clone();
+        self.books.lock().unwrap().insert(sym.clone(), outputs);
+        self.book_tasks.lock().unwrap().insert(sym.clone(), task);
+        info!(symbol=%sym, "Symbol added");
+
+        Ok(rx_ob)
+    }
+
+    /// Unsubscribes `symbol` and retires its order book task, waiting for the
+    /// task to drain its queue and exit.
+    ///
+    /// Existing receivers for the symbol then see `changed()` return an error.
+    pub async fn remove_symbol(&self, symbol: &str) -> Result<(), SubscriptionError> {
+        let sym = symbol.to_ascii_uppercase();
+
+        let _guard = self.control_lock.lock().await;
+        if !self.books.lock().unwrap().contains_key(&sym) {
+            return Err(SubscriptionError::NotSubscribed(sym));
+        }
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.router_tx
+            .send(RouterCommand::Unsubscribe {
+                symbol: sym.clone(),
+                ack: ack_tx,
+            })
+            .await
+            .map_err(|_| SubscriptionError::RouterClosed)?;
+        ack_rx.await.map_err(|_| SubscriptionError::RouterClosed)?;
+
+        self.books.lock().unwrap().remove(&sym);
+        let task = self.book_tasks.lock().unwrap().remove(&sym);
+        if let Some(task) = task {
+            if let Err(e) = task.await {
+                warn!(symbol=%sym, error=%e, "Orderbook task failed");
+            }
+        }
+        info!(symbol=%sym, "Symbol removed");
+
+        Ok(())
+    }
+
+    /// Stops the whole pipeline and waits for every spawned task to finish.
+    ///
+    /// Both websockets are closed with a Close frame, book tasks apply the
+    /// `DepthUpdate`s already queued for them and then exit. Book receivers
+    /// keep their last value. Calling it again is a no-op.
+    pub async fn shutdown(&self) {
+        let _guard = self.control_lock.lock().await;
+        let Some(router) = self.router.lock().await.take() else {
+            return;
+        };
+        info!("Shutting down");
+        router.shutdown().await;
+
+        let tasks: Vec<(String, JoinHandle<()>)> =
+            self.book_tasks.lock().unwrap().drain().collect();
+        for (sym, task) in tasks {
+            if let Err(e) = task.await {
+                warn!(symbol=%sym, error=%e, "Orderbook task failed");
+            }
+        }
+        self.books.lock().unwrap().clear();
+        info!("Shutdown complete");
+    }
+}
+
+/// Why [`StreamHandle::add_symbol`] or [`StreamHandle::remove_symbol`] failed.
+#[derive(Debug)]
+pub enum SubscriptionError {
+    InvalidSymbol(String),
+    UnsupportedStream(DepthStream),
+    /// The stream delivers fewer levels than [`StreamConfig::min_book_levels`].
+    TooShallow(DepthStream),
+    /// `exchangeInfo` lookup failed or rejected the symbol (tick ladder mode).
+    ExchangeInfo(Error),
+    AlreadySubscribed(String),
+    NotSubscribed(String),
+    RouterClosed,
+}
+
+impl fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscriptionError::InvalidSymbol(s) => write!(f, "invalid symbol {s:?}"),
+            SubscriptionError::UnsupportedStream(stream) => {
+                write!(f, "{} is not available on this market", stream.spec())
+            }
+            SubscriptionError::TooShallow(stream) => {
+                write!(f, "{} delivers fewer levels than min_book_levels", stream.spec())
+            }
+            SubscriptionError::ExchangeInfo(e) => write!(f, "{e}"),
+            SubscriptionError::AlreadySubscribed(s) => write!(f, "{s} is already subscribed"),
+            SubscriptionError::NotSubscribed(s) => write!(f, "{s} is not subscribed"),
+            SubscriptionError::RouterClosed => write!(f, "router task is no longer running"),
+        }
+    }
+}
+
+impl std::error::Error for SubscriptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SubscriptionError::ExchangeInfo(e) => Some(e),
+            _ => None,
+        }
+    }
+}
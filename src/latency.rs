@@ -0,0 +1,291 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time;
+
+use crate::ob_manager::order_book::DepthUpdate;
+
+/// Histograms rotate every `WINDOW`; stats cover the current and previous window.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Upper bucket bounds in microseconds; one extra bucket catches the rest.
+const BOUNDS_US: [i64; 16] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000,
+];
+
+/// When the WS reader received a message: monotonic for in-process
+/// latency, wall clock (ms since epoch) for comparing with exchange times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceiveStamp {
+    pub instant: Instant,
+    pub wall_ms: u64,
+}
+
+impl ReceiveStamp {
+    pub(crate) fn now() -> Self {
+        let wall_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            instant: Instant::now(),
+            wall_ms,
+        }
+    }
+}
+
+/// Summary of a [`LatencyHistogram`], in microseconds.
+///
+/// Percentiles are bucket upper bounds, so they overestimate by at most one
+/// bucket. Negative samples mean the local clock is behind the exchange's;
+/// they count towards `negative` and `min_us` but fall in the lowest bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub negative: u64,
+    pub min_us: i64,
+    pub max_us: i64,
+    pub mean_us: i64,
+    pub p50_us: i64,
+    pub p90_us: i64,
+    pub p99_us: i64,
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    // Tokio's clock, so tests can pause it; the same as `Instant` otherwise.
+    started: time::Instant,
+    buckets: [u64; BOUNDS_US.len() + 1],
+    count: u64,
+    negative: u64,
+    sum_us: i128,
+    min_us: i64,
+    max_us: i64,
+}
+
+impl Window {
+    fn new(started: time::Instant) -> Self {
+        Self {
+            started,
+            buckets: [0; BOUNDS_US.len() + 1],
+            count: 0,
+            negative: 0,
+            sum_us: 0,
+            min_us: i64::MAX,
+            max_us: i64::MIN,
+        }
+    }
+}
+
+/// Fixed-bucket latency histogram over a rolling one-to-two minute window.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    current: Window,
+    previous: Option<Window>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            current: Window::new(time::Instant::now()),
+            previous: None,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record_us(&mut self, us: i64) {
+        let now = time::Instant::now();
+        let age = now.duration_since(self.current.started);
+        if age >= 2 * WINDOW {
+            // Quiet for a whole window; nothing recorded is recent any more.
+            self.current = Window::new(now);
+            self.previous = None;
+        } else if age >= WINDOW {
+            let fresh = Window::new(now);
+            self.previous = Some(std::mem::replace(&mut self.current, fresh));
+        }
+        let w = &mut self.current;
+        let bucket = BOUNDS_US
+            .iter()
+            .position(|&b| us <= b)
+            .unwrap_or(BOUNDS_US.len());
+        w.buckets[bucket] += 1;
+        w.count += 1;
+        w.negative += u64::from(us < 0);
+        w.sum_us += i128::from(us);
+        w.min_us = w.min_us.min(us);
+        w.max_us = w.max_us.max(us);
+    }
+
+    pub fn stats(&self) -> LatencyStats {
+        // Windows only rotate on `record_us`; skip those a rotation now
+        // would already have dropped.
+        let age = self.current.started.elapsed();
+        let mut merged = if age < 2 * WINDOW {
+            self.current.clone()
+        } else {
+            Window::new(self.current.started)
+        };
+        if let Some(prev) = self.previous.as_ref().filter(|_| age < WINDOW) {
+            for (m, p) in merged.buckets.iter_mut().zip(prev.buckets) {
+                *m += p;
+            }
+            merged.count += prev.count;
+            merged.negative += prev.negative;
+            merged.sum_us += prev.sum_us;
+            merged.min_us = merged.min_us.min(prev.min_us);
+            merged.max_us = merged.max_us.max(prev.max_us);
+        }
+        if merged.count == 0 {
+            return LatencyStats::default();
+        }
+
+        let percentile = |q: f64| {
+            let rank = ((merged.count as f64) * q).ceil().max(1.0) as u64;
+            let mut seen = 0;
+            for (i, n) in merged.buckets.iter().enumerate() {
+                seen += n;
+                if seen >= rank {
+                    return BOUNDS_US.get(i).copied().unwrap_or(merged.max_us);
+                }
+            }
+            merged.max_us
+        };
+
+        LatencyStats {
+            count: merged.count,
+            negative: merged.negative,
+            min_us: merged.min_us,
+            max_us: merged.max_us,
+            mean_us: (merged.sum_us / i128::from(merged.count)) as i64,
+            p50_us: percentile(0.50),
+            p90_us: percentile(0.90),
+            p99_us: percentile(0.99),
+        }
+    }
+}
+
+/// Latency histograms of one symbol, recorded by its book task.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolLatency {
+    /// Local receive wall time − `E`: network plus clock skew.
+    pub exchange_to_socket: LatencyHistogram,
+    /// WS reader receive → applied to the book: routing and queueing.
+    pub socket_to_book: LatencyHistogram,
+    /// `E` − `T`: exchange-internal publish delay (futures only).
+    pub transaction_to_event: LatencyHistogram,
+}
+
+impl SymbolLatency {
+    pub(crate) fn record(&mut self, du: &DepthUpdate, applied: bool) {
+        if let Some(stamp) = du.received {
+            if du.E > 0 {
+                self.exchange_to_socket
+                    .record_us((stamp.wall_ms as i64 - du.E as i64) * 1_000);
+            }
+            if applied {
+                self.socket_to_book
+                    .record_us(stamp.instant.elapsed().as_micros() as i64);
+            }
+        }
+        if let (Some(t), true) = (du.T, du.E > 0) {
+            self.transaction_to_event
+                .record_us((du.E as i64 - t as i64) * 1_000);
+        }
+    }
+
+    pub fn stats(&self) -> SymbolLatencyStats {
+        SymbolLatencyStats {
+            exchange_to_socket: self.exchange_to_socket.stats(),
+            socket_to_book: self.socket_to_book.stats(),
+            transaction_to_event: self.transaction_to_event.stats(),
+        }
+    }
+}
+
+/// Snapshot of a [`SymbolLatency`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SymbolLatencyStats {
+    pub exchange_to_socket: LatencyStats,
+    pub socket_to_book: LatencyStats,
+    pub transaction_to_event: LatencyStats,
+}
+
+/// One of the two router connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Connection {
+    A,
+    B,
+}
+
+/// Exchange → socket latency per connection, recorded by the router for
+/// every event either socket delivers (parked or forwarded).
+#[derive(Debug, Default)]
+pub struct ConnectionLatency {
+    a: Mutex<LatencyHistogram>,
+    b: Mutex<LatencyHistogram>,
+}
+
+impl ConnectionLatency {
+    pub(crate) fn record(&self, conn: Connection, du: &DepthUpdate) {
+        let (Some(stamp), true) = (du.received, du.E > 0) else {
+            return;
+        };
+        self.histogram(conn)
+            .lock()
+            .unwrap()
+            .record_us((stamp.wall_ms as i64 - du.E as i64) * 1_000);
+    }
+
+    pub fn stats(&self, conn: Connection) -> LatencyStats {
+        self.histogram(conn).lock().unwrap().stats()
+    }
+
+    fn histogram(&self, conn: Connection) -> &Mutex<LatencyHistogram> {
+        match conn {
+            Connection::A => &self.a,
+            Connection::B => &self.b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn samples_age_out_of_the_window() {
+        let mut h = LatencyHistogram::default();
+        for us in [200, 800, 3_000] {
+            h.record_us(us);
+        }
+        let stats = h.stats();
+        assert_eq!((stats.count, stats.min_us, stats.max_us), (3, 200, 3_000));
+        assert_eq!((stats.p50_us, stats.p99_us), (1_000, 5_000));
+
+        // Rotated into the previous window: still counted.
+        time::advance(WINDOW + Duration::from_secs(1)).await;
+        h.record_us(-500);
+        let stats = h.stats();
+        assert_eq!((stats.count, stats.negative, stats.min_us), (4, 1, -500));
+
+        // The first three are now more than a window old.
+        time::advance(WINDOW).await;
+        let stats = h.stats();
+        assert_eq!((stats.count, stats.max_us), (1, -500));
+
+        time::advance(WINDOW).await;
+        assert_eq!(h.stats(), LatencyStats::default());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recording_after_a_quiet_spell_starts_over() {
+        let mut h = LatencyHistogram::default();
+        h.record_us(1_000);
+        time::advance(2 * WINDOW).await;
+        h.record_us(50);
+        let stats = h.stats();
+        assert_eq!((stats.count, stats.min_us, stats.max_us), (1, 50, 50));
+    }
+}
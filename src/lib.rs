@@ -5,25 +5,26 @@
 //! ## Quick start
 //!
 //! ```no_run
-//! use binance_stream_handler::generate_orderbooks;
+//! use binance_stream_handler::{generate_orderbooks, StreamConfig};
 //! use chrono::NaiveTime;
 //!
-//! // Currency pairs must be defined as a 'static slice.
-//! pub static CURRENCY_PAIRS: &[&str] = &["ADAUSDT", "DOGEUSDT"];
-//!
 //! #[tokio::main(flavor = "multi_thread")]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let cutoffs = (NaiveTime::from_hms_opt(2,0,0).unwrap(),
-//!                    NaiveTime::from_hms_opt(18,42,0).unwrap());
+//!     let config = StreamConfig::builder(["ADAUSDT", "DOGEUSDT"])
+//!         .switch_cutoffs(NaiveTime::from_hms_opt(2,0,0).unwrap(),
+//!                         NaiveTime::from_hms_opt(18,42,0).unwrap())
+//!         .chan_cap(1024)
+//!         .park_cap(512)
+//!         .build()?;
 //!
-//!     let streams = generate_orderbooks(CURRENCY_PAIRS, 1024, 512, cutoffs);
+//!     let handle = generate_orderbooks(config).await?;
 //!
-//!     let mut ada = streams["ADAUSDT"].clone();
+//!     let mut ada = handle.order_book("ADAUSDT").unwrap();
 //!     tokio::spawn(async move {
 //!         while ada.changed().await.is_ok() {
-//!             let ob = ada.borrow().clone();
-//!             println!("{} best bid={:?} ask={:?}",
-//!                      ob.symbol, ob.bids.keys().last(), ob.asks.keys().next());
+//!             let ob = ada.borrow();
+//!             println!("{} best bid={:?} ask={:?} spread={:?}bps",
+//!                      ob.symbol, ob.best_bid(), ob.best_ask(), ob.spread_bps());
 //!         }
 //!     });
 //!
@@ -40,47 +41,284 @@
 //! ```text
 //! struct OrderBook {
 //!     symbol: String,                     // e.g. "ADAUSDT"
-//!     bids: BTreeMap<Price, Qty>,         // sorted descending
-//!     asks: BTreeMap<Price, Qty>,         // sorted ascending
+//!     bids: Levels,                       // OrdMap<Price, Qty>, ascending
+//!     asks: Levels,                       // OrdMap<Price, Qty>, ascending
 //!     last_u: Option<u64>,                // last update ID applied
 //!     snapshot_id: Option<u64>,           // REST snapshot ID
-//!     depth: u16                          // snapshot depth (default 1000)
+//!     depth: u16,                         // snapshot depth (default 1000)
+//!     filters: Option<Arc<SymbolFilters>>, // tick/lot size (tick ladder mode)
+//!     ticks: Option<TickLadder>,          // levels as integer ticks → lots
+//!     last_event_time: Option<u64>,       // E of the last applied update
+//!     last_transaction_time: Option<u64>, // T of the last applied update
+//!     last_received: Option<ReceiveStamp>, // local receive time of it
 //! }
 //! ```
 //!
-//! - **`bids`**: map from price → quantity, sorted by price descending  
-//! - **`asks`**: map from price → quantity, sorted by price ascending  
+//! - **`bids`**: map from price → quantity; iterate best first with [`OrderBook::bids_desc`]  
+//! - **`asks`**: map from price → quantity; iterate best first with [`OrderBook::asks_asc`]  
 //! - **`last_u`**: last WebSocket update sequence number applied  
 //! - **`snapshot_id`**: ID of the REST snapshot used to initialize the book  
 //! - **`depth`**: the configured maximum depth (default: 1000)  
 //!
-//! You normally just clone the latest `OrderBook` from a `watch::Receiver` and
-//! inspect the maps to get the best bid/ask or traverse the book.
-
-use chrono::NaiveTime;
-
+//! [`Price`] is `OrderedFloat<f64>` and [`Qty`] is `f64` by default. Enable
+//! the `decimal` feature to use exact `rust_decimal::Decimal` for both, so
+//! prices never drift and zero-quantity removals always match their level.
+//!
+//! Both sides are persistent [`Levels`] maps (`im::OrdMap`) that share
+//! structure between versions, so cloning a book out of the channel is O(1)
+//! no matter how deep it is, and applying an update stays O(log n).
+//!
+//! You normally borrow the latest `OrderBook` from a `watch::Receiver` and use
+//! its query methods: [`best_bid`](OrderBook::best_bid),
+//! [`best_ask`](OrderBook::best_ask), [`mid`](OrderBook::mid),
+//! [`spread`](OrderBook::spread), [`spread_bps`](OrderBook::spread_bps),
+//! [`microprice`](OrderBook::microprice), [`imbalance`](OrderBook::imbalance),
+//! cumulative depth ([`bid_depth_to`](OrderBook::bid_depth_to),
+//! [`ask_depth_to`](OrderBook::ask_depth_to)) and the best-first level
+//! iterators [`bids_desc`](OrderBook::bids_desc) and
+//! [`asks_asc`](OrderBook::asks_asc).
+//!
+//! [`simulate_market_order`](OrderBook::simulate_market_order) and
+//! [`simulate_notional`](OrderBook::simulate_notional) estimate the cost of
+//! a market order of a given base size or quote amount: average and worst
+//! fill price, slippage against the touch, levels consumed and any size the
+//! book is too thin for.
+//!
+//! [`aggregate`](OrderBook::aggregate) groups levels into fixed-step or
+//! bps-from-mid [`PriceBucket`]s for depth charts and risk views, rounding
+//! bids down and asks up.
+//!
+//! ## Book events
+//!
+//! The `watch` channel only holds the latest book. For every intermediate
+//! change, [`StreamHandle::book_events`] gives a broadcast receiver of
+//! [`BookEvent`]s: a [`BookDelta`] per applied update (each changed level
+//! with side, price, old and new quantity, plus `U`/`u`/`E`/`T`), and an
+//! explicit `Reset` carrying the new book whenever it is rebuilt from a
+//! snapshot.
+//!
+//! Strategies that only need the touch can use [`StreamHandle::bbo_events`]
+//! instead: a [`BboEvent`] with the previous and new [`Bbo`], the update id
+//! and event time, emitted only when the best bid or ask moves.
+//!
+//! ## Feed status
+//!
+//! [`StreamHandle::feed_status`] publishes a [`FeedHealth`] per symbol next
+//! to its book: a [`FeedStatus`] (`Initializing`, `Live`, `Resyncing`,
+//! `Stale`, `Degraded { attempts, reason }` or `Failed { reason }`) plus the
+//! wall time and exchange event time of the last applied update. A book
+//! awaiting resync is no longer reported live.
+//!
+//! Failed snapshot fetches are retried with exponential backoff and jitter
+//! per [`StreamConfigBuilder::snapshot_retry`], honouring `Retry-After`;
+//! the book reports `Degraded` meanwhile. A [`RetryPolicy`] can cap the
+//! attempts and call a hook with a [`SnapshotEscalation`] when failures pile
+//! up. Only non-retryable errors or an exhausted policy end in `Failed`.
+//!
+//! All books of a pipeline fetch snapshots through one [`SnapshotClient`],
+//! which keeps a single connection pool and spends request weight from a
+//! token bucket ([`StreamConfigBuilder::rest_weight_budget`], by default 80%
+//! of the market's per-minute limit). It follows the exchange's
+//! `X-MBX-USED-WEIGHT-1M` header, stops every request for the `Retry-After`
+//! of a 418/429, and lets resyncs of live books go before first snapshots,
+//! so a resync storm across hundreds of symbols queues up instead of getting
+//! the IP banned.
+//!
+//! The client is one [`SnapshotProvider`]. [`generate_orderbooks_with`]
+//! takes any other, such as [`FileSnapshots`] (recorded responses) or a
+//! [`SnapshotFn`] closure, to run the pipeline against deterministic
+//! snapshots or a local stand-in. Likewise the router's A and B connections
+//! come from a [`DepthSource`]: [`WsSource`] by default, or a
+//! [`ChannelSource`] fed in memory or a [`ReplaySource`] playing back a
+//! recorded stream, so the A/B handover can be driven without a network.
+//!
+//! Books are bootstrapped the way Binance documents it, at startup and on
+//! every resync: updates that arrive while the REST snapshot is in flight
+//! are buffered (up to [`StreamConfig::resync_buffer_cap`]), those with
+//! `u < lastUpdateId` are dropped, and the first one applied is the one
+//! bridging `lastUpdateId`. The update that triggered a resync is kept and
+//! replayed against the new snapshot, so nothing is lost or applied twice.
+//!
+//! Every snapshot and every applied update is also checked for states that
+//! can't be right: a crossed or locked top of book, a zero, negative or NaN
+//! quantity, or a side with fewer than [`StreamConfig::min_book_levels`]
+//! levels. A failed check is logged with its [`IntegrityViolation`], counted
+//! as a resync with [`ResyncCause::Integrity`], and forces a fresh snapshot;
+//! the offending update is replayed against it. A book failing the check is
+//! never published, nor are its delta and BBO events. A snapshot failing the
+//! check is refetched with the [`StreamConfig::snapshot_retry`] backoff, the
+//! feed reporting `Degraded` meanwhile.
+//!
+//! A router connection that drops (Close frame, network error, server
+//! disconnect) is reopened with backoff per
+//! [`StreamConfigBuilder::ws_reconnect`] and resubscribed to every stream.
+//! During the A/B overlap the other connection takes over straight away.
+//! Otherwise the symbols it delivered go `Resyncing` and, once updates
+//! resume, are bootstrapped again ([`ResyncCause::ConnectionLost`]).
+//! Connections are dialed alongside the open one, which keeps flowing, and
+//! at a cutoff the outgoing connection is only closed once the incoming one
+//! is up. [`StreamHandle::connection_events`] reports each
+//! [`ConnectionEvent`].
+//!
+//! ## Latency
+//!
+//! The WS reader stamps every message with a [`ReceiveStamp`], and each book
+//! keeps the `E`/`T` times and receive stamp of its last applied update.
+//! [`StreamHandle::latency`] reports rolling histograms per symbol for
+//! exchange → socket, socket → book and `T` → `E`;
+//! [`StreamHandle::connection_latency`] reports exchange → socket per router
+//! connection (A or B). Negative exchange → socket samples point at local
+//! clock skew.
+//!
+//! ## Metrics
+//!
+//! With the `metrics` feature, [`serve_metrics`] serves a Prometheus
+//! `/metrics` endpoint on a local port: updates applied and dropped per
+//! symbol, resyncs by cause, snapshot fetch latency and failures, router
+//! mode transitions, park buffer occupancy, router → book channel fill, WS
+//! connection attempts, reconnects and parse failures.
+//!
+//! ## Configuration
+//!
+//! Everything else — base URLs (e.g. testnet or a local stand-in server),
+//! the depth stream, snapshot depth and the A/B overlap window — is set
+//! through [`StreamConfig::builder`] and validated before anything is spawned.
+//!
+//! [`DepthStream`] picks the stream per symbol: the diff stream at the
+//! exchange's speeds (`@depth`, `@depth@100ms/250ms/500ms`), or a partial
+//! top-5/10/20 stream. Partial books skip the REST snapshot and continuity
+//! checks and are simply replaced on every message.
+//!
+//! [`Market`] selects USD-M futures (the default), COIN-M futures or Spot.
+//! COIN-M symbols keep their underscore (`BTCUSD_PERP`) as book keys. Spot
+//! books use the spot endpoints and the spot sequencing rule
+//! (`U == last_u + 1`, no `pu`) but flow through the same router and `watch`
+//! channels.
+//!
+//! With [`StreamConfigBuilder::tick_ladder`], `exchangeInfo` is fetched
+//! before connecting: unknown or non-trading symbols fail startup with
+//! [`Error::UnknownSymbol`], and each book additionally keeps a
+//! [`TickLadder`] of integer tick and lot counts. Updates with an off-grid
+//! price or quantity are rejected and trigger a resync.
+//!
+//! ## Adding and removing symbols at runtime
+//!
+//! [`generate_orderbooks`] returns a [`StreamHandle`]. Its
+//! [`add_symbol`](StreamHandle::add_symbol) and
+//! [`remove_symbol`](StreamHandle::remove_symbol) send Binance
+//! SUBSCRIBE/UNSUBSCRIBE requests on the live sockets and start or retire
+//! the symbol's book task, without restarting the router.
+//!
+//! ```no_run
+//! # async fn run(handle: binance_stream_handler::StreamHandle) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut sol = handle.add_symbol("SOLUSDT").await?;
+//! sol.changed().await?;
+//! handle.remove_symbol("DOGEUSDT").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Shutdown
+//!
+//! [`StreamHandle::shutdown`] closes both websockets with a Close frame, lets
+//! the book tasks drain already-queued updates and joins every spawned task,
+//! so pipelines can be restarted without leaking sockets.
+//!
+//! ## Errors
+//!
+//! Snapshot and stream APIs return [`Error`], which distinguishes rate limits
+//! (with `Retry-After`), other HTTP statuses with Binance's error code,
+//! transport failures, JSON decode errors, local file errors and WebSocket
+//! handshake rejections.
+//! [`Error::is_retryable`] gives a default classification for retry policies.
 
 use std::collections::HashMap;
-use tokio::sync::{watch};
+use std::sync::Arc;
 
+mod config;
+mod error;
+mod handle;
+mod latency;
 mod ob_manager;
+mod retry;
 mod router;
+mod telemetry;
 
-pub use crate::ob_manager::init_order_books;
-pub use crate::ob_manager::order_book::OrderBook;
+pub use crate::config::{ConfigError, DepthStream, Market, StreamConfig, StreamConfigBuilder};
+pub use crate::error::Error;
+pub use crate::handle::{StreamHandle, SubscriptionError};
+#[cfg(feature = "metrics")]
+pub use crate::telemetry::serve_metrics;
+pub use crate::latency::{
+    Connection, LatencyHistogram, LatencyStats, ReceiveStamp, SymbolLatency, SymbolLatencyStats,
+};
+pub use crate::ob_manager::aggregation::{AggregatedBook, AggregatedLevel, PriceBucket};
+pub use crate::ob_manager::events::{
+    Bbo, BboEvent, BookDelta, BookEvent, BookSide, LevelChange,
+};
+pub use crate::ob_manager::exchange_info::{fetch_symbol_filters, SymbolFilters};
+pub use crate::ob_manager::integrity::IntegrityViolation;
+pub use crate::ob_manager::{init_order_books, BookOutputs};
+pub use crate::ob_manager::order_book::{
+    price_to_f64, qty_to_f64, CombinedDepthUpdate, DepthSnapshot, DepthUpdate, Levels, OrderBook,
+    Price, Qty, ResyncCause, TickLadder,
+};
+pub use crate::ob_manager::simulation::{FillEstimate, Side};
+pub use crate::ob_manager::snapshot_client::{SnapshotClient, SnapshotPriority};
+pub use crate::ob_manager::snapshot_provider::{FileSnapshots, SnapshotFn, SnapshotProvider};
+pub use crate::ob_manager::status::{FeedHealth, FeedStatus};
+pub use crate::retry::{RetryPolicy, SnapshotEscalation};
+pub use crate::router::{ConnectionEvent, RouterMessage};
+pub use crate::router::source::{ChannelSource, DepthSource, ReplaySource};
+pub use crate::router::streaming::{LiveStream, WsSource};
 use crate::router::DualRouter;
 
-pub async fn generate_orderbooks(
-    currency_pairs: &'static [&'static str],
-    chan_cap: usize,
-    park_cap: usize,
-    switch_cutoffs: (NaiveTime, NaiveTime),
-) -> HashMap<String, watch::Receiver<OrderBook>> {
-    let dual_router = DualRouter::new(switch_cutoffs, currency_pairs);
-    let (receivers, connected) = dual_router.start_dual_router(chan_cap, park_cap);
+/// Connects the router and starts one book task per configured symbol.
+///
+/// Fails when the HTTP client cannot be built, or when
+/// [`StreamConfig::tick_ladder`] is on and `exchangeInfo` cannot be fetched
+/// or rejects one of the symbols.
+///
+/// Also fails when no WebSocket connection can be opened: straight away on
+/// an error that is not [retryable](Error::is_retryable), such as a 403 or
+/// 451 handshake rejection, otherwise once
+/// [`StreamConfigBuilder::ws_reconnect`] gives up.
+pub async fn generate_orderbooks(config: StreamConfig) -> Result<StreamHandle, Error> {
+    let snapshots = Arc::new(SnapshotClient::new(&config)?);
+    let depth = Arc::new(WsSource::new(&config));
+    generate_orderbooks_with(config, snapshots, depth).await
+}
+
+/// [`generate_orderbooks`] with snapshots from `snapshots` and depth events
+/// from `depth` instead of Binance, e.g. [`FileSnapshots`] or a
+/// [`SnapshotFn`] mock plus a [`ChannelSource`] or [`ReplaySource`].
+///
+/// `exchangeInfo` for [`StreamConfig::tick_ladder`] is loaded through
+/// [`SnapshotProvider::symbol_filters`], by default from
+/// [`StreamConfig::rest_base_url`].
+pub async fn generate_orderbooks_with(
+    config: StreamConfig,
+    snapshots: Arc<dyn SnapshotProvider>,
+    depth: Arc<dyn DepthSource>,
+) -> Result<StreamHandle, Error> {
+    let filters = if config.tick_ladder() {
+        snapshots
+            .symbol_filters(&config, config.currency_pairs(), SnapshotPriority::Initial)
+            .await?
+    } else {
+        HashMap::new()
+    };
+
+    let config = Arc::new(config);
+    let dual_router = DualRouter::new(config.clone(), depth);
+    let (receivers, started, router) = dual_router.start_dual_router();
 
-    connected.notified().await;
-    let ob_streams = init_order_books(currency_pairs, receivers);
+    if let Err(e) = started.await.expect("router task stopped before startup") {
+        router.shutdown().await;
+        return Err(e);
+    }
+    let (ob_streams, book_tasks) =
+        init_order_books(config.clone(), receivers, &filters, snapshots.clone());
 
-    ob_streams
+    Ok(StreamHandle::new(config, ob_streams, book_tasks, snapshots, router))
 }
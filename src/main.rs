@@ -2,7 +2,7 @@ use chrono::NaiveTime;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
-use binance_stream_handler::generate_orderbooks;
+use binance_stream_handler::{generate_orderbooks, StreamConfig};
 
 pub static CURRENCY_PAIRS: &[&str] = &[
     "ADAUSDT",
@@ -29,12 +29,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         NaiveTime::from_hms_opt(2, 0, 0).unwrap(),   // 02:00
         NaiveTime::from_hms_opt(18, 42, 0).unwrap(), // 14:00
     );
-    let chan_cap = 1024;
-    let park_cap = 512;
+    let config = StreamConfig::builder(CURRENCY_PAIRS)
+        .switch_cutoffs(switch_cutoffs.0, switch_cutoffs.1)
+        .chan_cap(1024)
+        .park_cap(512)
+        .build()?;
 
-    let ob_streams = generate_orderbooks(CURRENCY_PAIRS, chan_cap, park_cap, switch_cutoffs).await;
+    let handle = generate_orderbooks(config).await?;
 
-    let mut ada_rx = ob_streams.get("ADAUSDT").unwrap().clone();
+    let mut ada_rx = handle.order_book("ADAUSDT").unwrap();
 
     tokio::spawn(async move {
         loop {
@@ -42,18 +45,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 break;
             }
 
-            let latest = ada_rx.borrow().clone();
+            let latest = ada_rx.borrow();
 
             println!(
-                "[{}] best bid={:?}, best ask={:?}, last_u={:?}",
+                "[{}] best bid={:?}, best ask={:?}, mid={:?}, last_u={:?}",
                 latest.symbol,
-                latest.bids.keys().last(),
-                latest.asks.keys().next(),
+                latest.best_bid(),
+                latest.best_ask(),
+                latest.mid(),
                 latest.last_u,
             );
         }
     });
 
-    futures_util::future::pending::<()>().await;
+    tokio::signal::ctrl_c().await?;
+    info!("Ctrl-C received, shutting down");
+    handle.shutdown().await;
     Ok(())
 }
@@ -1,90 +1,645 @@
-use std::collections::HashMap;
-use tokio::sync::{mpsc, watch};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
 use tracing::info_span;
 use tracing::Instrument;
 use tracing::{debug, error, info, trace, warn};
 
+mod analytics;
+pub mod aggregation;
+pub mod events;
+pub mod exchange_info;
+pub mod integrity;
 pub mod order_book;
+pub mod simulation;
+pub mod snapshot_client;
+pub mod snapshot_provider;
+pub mod status;
 
-use crate::ob_manager::order_book::{DepthUpdate, OrderBook, UpdateDecision};
+use crate::config::{DepthStream, StreamConfig};
+use crate::error::Error;
+use crate::latency::SymbolLatency;
+use crate::retry::SnapshotEscalation;
+use crate::router::RouterMessage;
+use crate::telemetry::{self, BookMetrics};
+use crate::ob_manager::events::{Bbo, BboEvent, BookDelta, BookEvent};
+use crate::ob_manager::exchange_info::SymbolFilters;
+use crate::ob_manager::integrity::IntegrityViolation;
+use crate::ob_manager::order_book::{DepthUpdate, OrderBook, ResyncCause, UpdateDecision};
+use crate::ob_manager::snapshot_client::SnapshotPriority;
+use crate::ob_manager::snapshot_provider::SnapshotProvider;
+use crate::ob_manager::status::{mark_applied, mark_stale, set_status, FeedHealth, FeedStatus};
 
+/// What one symbol's book task publishes.
+#[derive(Debug, Clone)]
+pub struct BookOutputs {
+    /// Latest book.
+    pub book: watch::Receiver<OrderBook>,
+    /// Every applied delta and snapshot reset; call `subscribe()` to listen.
+    pub events: broadcast::Sender<BookEvent>,
+    /// Top-of-book changes only.
+    pub bbo: broadcast::Sender<BboEvent>,
+    /// Feed health; notified on status transitions.
+    pub status: watch::Receiver<FeedHealth>,
+    /// Latency histograms, recorded on every update the task receives.
+    pub latency: Arc<Mutex<SymbolLatency>>,
+}
+
+/// Spawns one book task per configured symbol.
+///
+/// Returns the book outputs and the task handles, both keyed by symbol.
+/// Symbols with an entry in `filters` also keep an integer tick ladder. All
+/// books fetch their snapshots from the shared `provider`.
 pub fn init_order_books(
-    currency_pairs: &'static [&'static str],
-    mut receivers: HashMap<String, mpsc::Receiver<DepthUpdate>>,
-) -> HashMap<String, watch::Receiver<OrderBook>> {
-    let mut ob_streams: HashMap<String, watch::Receiver<OrderBook>> = HashMap::new();
-
-    for &pair in currency_pairs {
-        let pair_up = pair.to_ascii_uppercase();
-        let mut rx = receivers
-            .remove(&pair_up)
+    config: Arc<StreamConfig>,
+    mut receivers: HashMap<String, mpsc::Receiver<RouterMessage>>,
+    filters: &HashMap<String, SymbolFilters>,
+    provider: Arc<dyn SnapshotProvider>,
+) -> (HashMap<String, BookOutputs>, HashMap<String, JoinHandle<()>>) {
+    let mut ob_streams: HashMap<String, BookOutputs> = HashMap::new();
+    let mut tasks: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    for pair in config.currency_pairs() {
+        let rx = receivers
+            .remove(pair)
             .expect("router created a channel for every symbol");
 
-        let (tx_ob, rx_ob) = watch::channel(OrderBook::new(pair));
-        ob_streams.insert(pair_up.clone(), rx_ob);
+        let stream = config.depth_stream_for(pair);
+        let (outputs, task) = spawn_order_book(
+            pair.clone(),
+            config.clone(),
+            stream,
+            filters.get(pair).cloned(),
+            provider.clone(),
+            rx,
+        );
+        ob_streams.insert(pair.clone(), outputs);
+        tasks.insert(pair.clone(), task);
+    }
+    (ob_streams, tasks)
+}
 
-        tokio::spawn(
-            async move {
-                let ob = match OrderBook::init_ob(pair).await {
-                    Ok(ob) => {
-                        debug!(
-                            "{} orderbook is initiated, last Id: {:?}",
-                            ob.symbol, ob.snapshot_id
-                        );
-                        ob
+/// Spawns the task that keeps one symbol's book in sync with its router channel.
+///
+/// The task ends (and the returned receiver sees the sender drop) once the
+/// router side of `rx` is closed, e.g. after the symbol is unsubscribed.
+pub(crate) fn spawn_order_book(
+    pair: String,
+    config: Arc<StreamConfig>,
+    stream: DepthStream,
+    filters: Option<SymbolFilters>,
+    provider: Arc<dyn SnapshotProvider>,
+    mut rx: mpsc::Receiver<RouterMessage>,
+) -> (BookOutputs, JoinHandle<()>) {
+    let mut empty = OrderBook::new(&pair);
+    empty.set_filters(filters.clone());
+    let (tx_ob, rx_ob) = watch::channel(empty);
+    let (events, _) = broadcast::channel(config.event_cap());
+    let (bbo_tx, _) = broadcast::channel(config.event_cap());
+    let (status_tx, status_rx) = watch::channel(FeedHealth::new());
+    let latency = Arc::new(Mutex::new(SymbolLatency::default()));
+    let outputs = BookOutputs {
+        book: rx_ob,
+        events: events.clone(),
+        bbo: bbo_tx.clone(),
+        status: status_rx,
+        latency: latency.clone(),
+    };
+    let mut last_bbo = Bbo::default();
+    let stale_after = config.stale_after();
+    let min_levels = config.min_book_levels();
+    let metrics = BookMetrics::new(&pair);
+    let span = info_span!("orderbook_task", symbol = %pair);
+    let task = tokio::spawn(
+        async move {
+            if stream.is_partial() {
+                // Top-N streams are full snapshots: no REST bootstrap, no sequencing.
+                while let Some(msg) = next_update(&mut rx, stale_after, &status_tx).await {
+                    let du = match msg {
+                        RouterMessage::Update(du) => du,
+                        RouterMessage::ConnectionLost => {
+                            // The next message replaces the whole book anyway.
+                            set_status(&status_tx, FeedStatus::Resyncing);
+                            continue;
+                        }
+                    };
+                    // Older than the book, e.g. the other connection's
+                    // backlog flushed at a handover.
+                    if tx_ob.borrow().last_u.is_some_and(|last_u| du.u <= last_u) {
+                        metrics.update_dropped();
+                        continue;
                     }
-                    Err(e) => {
-                        error!(symbole=%pair, error=%e, "Snapshot init failed; stopping orderbook task");
-                        return;
+                    // A rejected partial leaves the book behind; the next
+                    // accepted one brings it back to `Live`.
+                    if let Some([price, qty]) = tx_ob.borrow().off_grid_level(&du) {
+                        warn!(%price, %qty, u=du.u, "Off-grid level; partial update rejected");
+                        telemetry::resync(&pair, ResyncCause::OffGrid);
+                        set_status(&status_tx, FeedStatus::Resyncing);
+                        continue;
                     }
-                };
-                let mut need_resync = false;
-                let _ = tx_ob.send_replace(ob);
-
-                while let Some(du) = rx.recv().await {
-                    if need_resync {
-                        let fresh_ob = match OrderBook::init_ob(pair).await {
-                            Ok(ob) => ob,
-                            Err(e) => {
-                                error!(symbole=%pair, error=%e, "Snapshot re-init failed during resync; stopping orderbook task");
-                                return;
-                            }
-                        };
-                        let _ = tx_ob.send_replace(fresh_ob);
-                        need_resync = false;
-                    } else {
-                        let _ = tx_ob.send_modify(|book| match book.continuity_check(&du) {
-                            UpdateDecision::Drop => {
-                                info!(
-                                    symbol=%pair,
-                                    U=du.U, u=du.u, pu=du.pu,
-                                    snap_id=?book.snapshot_id.map(|x| x+1),
-                                    last_u=?book.last_u,
-                                    "Update dropped"
-                                );
-                            }
-                            UpdateDecision::Apply(du) => {
-                                trace!("Update applied");
-                                book.apply_update(du);
+                    let mut next = tx_ob.borrow().clone();
+                    next.replace_from_partial(&du);
+                    if let Some(v) = next.check_integrity(min_levels) {
+                        integrity_resync(&pair, v);
+                        set_status(&status_tx, FeedStatus::Resyncing);
+                        continue;
+                    }
+                    let _ = tx_ob.send_replace(next);
+                    latency.lock().unwrap().record(&du, true);
+                    metrics.update_applied();
+                    let event_time = Some(du.E).filter(|&e| e > 0);
+                    mark_applied(&status_tx, event_time);
+                    publish_reset(&events, &tx_ob);
+                    publish_bbo(&bbo_tx, &tx_ob, &mut last_bbo, event_time);
+                }
+                debug!("Router channel closed; orderbook task exiting");
+                set_status(&status_tx, FeedStatus::Stale);
+                return;
+            }
+
+            // Binance's bootstrap: updates that arrive while a snapshot is in
+            // flight wait in `pending`, then go through `continuity_check`
+            // like any other, which drops those the snapshot already covers
+            // and applies from the one bridging it.
+            let mut pending: VecDeque<DepthUpdate> = VecDeque::new();
+            let mut need_resync = true;
+            let mut priority = SnapshotPriority::Initial;
+            let mut filters = filters;
+            // Reload the filters before the next snapshot (an update was off-grid).
+            let mut reload_filters = false;
+            // An update was off-grid and none has applied since.
+            let mut off_grid = false;
+            loop {
+                if need_resync {
+                    let fetch = bootstrap_with_retry(&pair, &config, &mut filters, reload_filters, &*provider, priority, &status_tx);
+                    let fetched = bootstrap_buffered(&pair, &config, &mut rx, &mut pending, &metrics, fetch).await;
+                    let ob = match fetched {
+                        Some(Ok(ob)) => ob,
+                        Some(Err(e)) => {
+                            error!(symbol=%pair, error=%e, "Snapshot fetch failed for good; stopping orderbook task");
+                            fail(&status_tx, &mut rx, e.to_string()).await;
+                            return;
+                        }
+                        None => break,
+                    };
+                    reload_filters = false;
+                    debug!(
+                        last_update_id=?ob.snapshot_id,
+                        buffered=pending.len(),
+                        "Snapshot loaded"
+                    );
+                    let _ = tx_ob.send_replace(ob);
+                    publish_reset(&events, &tx_ob);
+                    publish_bbo(&bbo_tx, &tx_ob, &mut last_bbo, None);
+                    need_resync = false;
+                    priority = SnapshotPriority::Resync;
+                }
+
+                let du = match pending.pop_front() {
+                    Some(du) => du,
+                    None => match next_update(&mut rx, stale_after, &status_tx).await {
+                        Some(RouterMessage::Update(du)) => du,
+                        Some(RouterMessage::ConnectionLost) => {
+                            warn!(symbol=%pair, "Router connection lost; resyncing once updates resume");
+                            telemetry::resync(&pair, ResyncCause::ConnectionLost);
+                            set_status(&status_tx, FeedStatus::Resyncing);
+                            // A snapshot taken now would predate the updates
+                            // lost before the replacement connection is up.
+                            match next_depth_update(&mut rx, stale_after, &status_tx).await {
+                                Some(du) => pending.push_back(du),
+                                None => break,
                             }
-                            UpdateDecision::Resync(info) => {
-                                
-                                warn!(
-                                    expected_pu=?info.expected_pu,
-                                    got_pu=info.got_pu,
-                                    got_U=info.got_U,
-                                    got_u=info.got_u,
-                                    "Resync required"
-                                );
-
-                                need_resync = true;
+                            need_resync = true;
+                            continue;
+                        }
+                        None => break,
+                    },
+                };
+                // Work on a copy (O(1) with `im`) so a rejected update never
+                // reaches the watch receivers.
+                let mut next = tx_ob.borrow().clone();
+                let mut changes = None;
+                let mut applied = false;
+                match next.continuity_check(&du) {
+                    UpdateDecision::Drop => {
+                        info!(
+                            symbol=%pair,
+                            U=du.U, u=du.u, pu=?du.pu,
+                            snap_id=?next.snapshot_id.map(|x| x+1),
+                            last_u=?next.last_u,
+                            "Update dropped"
+                        );
+                        metrics.update_dropped();
+                    }
+                    UpdateDecision::Apply(du) => match next.off_grid_level(du) {
+                        None => {
+                            changes = if events.receiver_count() == 0 {
+                                next.apply_update(du);
+                                None
+                            } else {
+                                Some(next.apply_update_with_changes(du))
+                            };
+                            match next.check_update_integrity(du, min_levels) {
+                                Some(v) => {
+                                    integrity_resync(&pair, v);
+                                    need_resync = true;
+                                }
+                                None => applied = true,
                             }
-                        });
+                        }
+                        Some([price, qty]) if off_grid => {
+                            // Still off-grid with freshly loaded filters.
+                            error!(symbol=%pair, %price, %qty, u=du.u, "Off-grid level after reloading filters; stopping orderbook task");
+                            let reason = format!("level {qty} at {price} is off the tick/lot grid");
+                            fail(&status_tx, &mut rx, reason).await;
+                            return;
+                        }
+                        Some([price, qty]) => {
+                            warn!(%price, %qty, u=du.u, "Off-grid level; update rejected, reloading filters and resyncing");
+                            telemetry::resync(&pair, ResyncCause::OffGrid);
+                            need_resync = true;
+                            reload_filters = true;
+                            off_grid = true;
+                        }
+                    },
+                    UpdateDecision::Resync(info) => {
+                        telemetry::resync(&pair, info.cause);
+                        warn!(
+                            cause=info.cause.as_str(),
+                            expected_pu=?info.expected_pu,
+                            got_pu=info.got_pu,
+                            got_U=info.got_U,
+                            got_u=info.got_u,
+                            "Resync required"
+                        );
+                        need_resync = true;
                     }
                 }
+                if need_resync {
+                    // Newer than the book; the next snapshot decides whether
+                    // it is dropped or bridges.
+                    pending.push_front(du);
+                    set_status(&status_tx, FeedStatus::Resyncing);
+                    continue;
+                }
+                latency.lock().unwrap().record(&du, applied);
+                if !applied {
+                    continue;
+                }
+                trace!("Update applied");
+                off_grid = false;
+                let _ = tx_ob.send_replace(next);
+                if let Some(changes) = changes {
+                    let _ = events.send(BookEvent::Delta(Arc::new(BookDelta {
+                        symbol: pair.clone(),
+                        first_update_id: du.U,
+                        final_update_id: du.u,
+                        event_time: du.E,
+                        transaction_time: du.T,
+                        changes,
+                    })));
+                }
+                metrics.update_applied();
+                mark_applied(&status_tx, Some(du.E));
+                publish_bbo(&bbo_tx, &tx_ob, &mut last_bbo, Some(du.E));
             }
-            .instrument(info_span!("orderbook_task", symbol = %pair)),
+            debug!("Router channel closed; orderbook task exiting");
+            set_status(&status_tx, FeedStatus::Stale);
+        }
+        .instrument(span),
+    );
+    (outputs, task)
+}
+
+/// Next message from the router, marking the feed stale while none arrives.
+async fn next_update(
+    rx: &mut mpsc::Receiver<RouterMessage>,
+    stale_after: Duration,
+    status_tx: &watch::Sender<FeedHealth>,
+) -> Option<RouterMessage> {
+    loop {
+        match tokio::time::timeout(stale_after, rx.recv()).await {
+            Ok(msg) => return msg,
+            Err(_) => mark_stale(status_tx),
+        }
+    }
+}
+
+/// [`next_update`], skipping connection losses: the caller is about to
+/// resync anyway.
+async fn next_depth_update(
+    rx: &mut mpsc::Receiver<RouterMessage>,
+    stale_after: Duration,
+    status_tx: &watch::Sender<FeedHealth>,
+) -> Option<DepthUpdate> {
+    loop {
+        if let RouterMessage::Update(du) = next_update(rx, stale_after, status_tx).await? {
+            return Some(du);
+        }
+    }
+}
+
+/// Emits a [`BboEvent`] if the top of the book in `tx_ob` moved since `last`.
+fn publish_bbo(
+    bbo_tx: &broadcast::Sender<BboEvent>,
+    tx_ob: &watch::Sender<OrderBook>,
+    last: &mut Bbo,
+    event_time: Option<u64>,
+) {
+    let book = tx_ob.borrow();
+    let new = book.bbo();
+    if new == *last {
+        return;
+    }
+    let prev = std::mem::replace(last, new);
+    if bbo_tx.receiver_count() > 0 {
+        let _ = bbo_tx.send(BboEvent {
+            symbol: book.symbol.clone(),
+            prev,
+            new,
+            update_id: book.last_u.or(book.snapshot_id).unwrap_or_default(),
+            event_time,
+        });
+    }
+}
+
+/// Logs and counts an integrity failure; the caller schedules the resync.
+fn integrity_resync(pair: &str, violation: IntegrityViolation) {
+    warn!(symbol=%pair, reason=%violation, "Book integrity violated; resyncing");
+    telemetry::resync(pair, ResyncCause::Integrity(violation));
+}
+
+/// Announces the book now in `tx_ob` as a fresh base for event consumers.
+fn publish_reset(events: &broadcast::Sender<BookEvent>, tx_ob: &watch::Sender<OrderBook>) {
+    if events.receiver_count() > 0 {
+        let _ = events.send(BookEvent::Reset(tx_ob.borrow().clone()));
+    }
+}
+
+/// Drives the snapshot `fetch`, queueing the updates that arrive meanwhile
+/// onto `pending` (oldest dropped beyond [`StreamConfig::resync_buffer_cap`]).
+/// A connection loss empties the queue; what follows can't bridge it.
+///
+/// `None` if the router channel closes first.
+async fn bootstrap_buffered(
+    pair: &str,
+    config: &StreamConfig,
+    rx: &mut mpsc::Receiver<RouterMessage>,
+    pending: &mut VecDeque<DepthUpdate>,
+    metrics: &BookMetrics,
+    fetch: impl Future<Output = Result<OrderBook, Error>>,
+) -> Option<Result<OrderBook, Error>> {
+    let cap = config.resync_buffer_cap();
+    tokio::pin!(fetch);
+    loop {
+        tokio::select! {
+            res = &mut fetch => return Some(res),
+            msg = rx.recv() => {
+                let du = match msg? {
+                    RouterMessage::Update(du) => du,
+                    RouterMessage::ConnectionLost => {
+                        debug!(symbol=%pair, dropped=pending.len(), "Router connection lost during snapshot fetch");
+                        pending.clear();
+                        continue;
+                    }
+                };
+                if pending.len() >= cap {
+                    warn!(symbol=%pair, cap, "Resync buffer full; dropping oldest update");
+                    pending.pop_front();
+                    metrics.update_dropped();
+                }
+                pending.push_back(du);
+            }
+        }
+    }
+}
+
+/// [`bootstrap`] retried per [`StreamConfig::snapshot_retry`], with the
+/// feed marked `Degraded` between attempts.
+///
+/// The symbol's filters are reloaded first if `reload` is set, and once
+/// more if the snapshot is off their grid (e.g. after a tick size change);
+/// still off-grid after that is final.
+#[allow(clippy::too_many_arguments)]
+async fn bootstrap_with_retry(
+    pair: &str,
+    config: &StreamConfig,
+    filters: &mut Option<SymbolFilters>,
+    mut reload: bool,
+    provider: &dyn SnapshotProvider,
+    priority: SnapshotPriority,
+    status_tx: &watch::Sender<FeedHealth>,
+) -> Result<OrderBook, Error> {
+    let policy = config.snapshot_retry();
+    let mut failures = 0;
+    let mut reloaded = false;
+    loop {
+        let result = if reload && filters.is_some() {
+            match reload_filters(pair, config, filters, provider).await {
+                Ok(()) => {
+                    reload = false;
+                    reloaded = true;
+                    bootstrap(pair, config, filters, provider, priority).await
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            bootstrap(pair, config, filters, provider, priority).await
+        };
+        let error = match result {
+            Ok(ob) => return Ok(ob),
+            Err(e) => e,
+        };
+        if !reloaded
+            && filters.is_some()
+            && matches!(
+                error,
+                Error::Integrity {
+                    violation: IntegrityViolation::OffGrid { .. },
+                    ..
+                }
+            )
+        {
+            warn!(symbol=%pair, %error, "Snapshot off the tick/lot grid; reloading filters");
+            reload = true;
+            continue;
+        }
+        failures += 1;
+        let gave_up =
+            !error.is_retryable() || policy.max_attempts.is_some_and(|max| failures >= max);
+        if gave_up || failures == policy.escalate_after {
+            policy.escalate(&SnapshotEscalation {
+                symbol: pair,
+                attempts: failures,
+                error: &error,
+                gave_up,
+            });
+        }
+        if gave_up {
+            return Err(error);
+        }
+        let delay = policy.delay(failures, error.retry_after());
+        warn!(symbol=%pair, %error, attempts=failures, ?delay, "Snapshot fetch failed; retrying");
+        set_status(
+            status_tx,
+            FeedStatus::Degraded {
+                attempts: failures,
+                reason: error.to_string(),
+            },
         );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Reloads `pair`'s tick/lot filters from `exchangeInfo`.
+async fn reload_filters(
+    pair: &str,
+    config: &StreamConfig,
+    filters: &mut Option<SymbolFilters>,
+    provider: &dyn SnapshotProvider,
+) -> Result<(), Error> {
+    let mut fresh = provider
+        .symbol_filters(config, &[pair.to_string()], SnapshotPriority::Resync)
+        .await?;
+    let fresh = fresh.remove(pair);
+    if let Some(f) = &fresh {
+        info!(symbol=%pair, tick=%f.tick_size, step=%f.step_size, "Symbol filters reloaded");
+    }
+    *filters = fresh;
+    Ok(())
+}
+
+/// Marks the feed `Failed`, then drains `rx` so the router isn't left
+/// sending into a closed channel.
+async fn fail(
+    status_tx: &watch::Sender<FeedHealth>,
+    rx: &mut mpsc::Receiver<RouterMessage>,
+    reason: String,
+) {
+    set_status(status_tx, FeedStatus::Failed { reason });
+    while rx.recv().await.is_some() {}
+}
+
+/// Fresh book from a provider snapshot, with the symbol's filters attached.
+///
+/// A snapshot failing the integrity check is an [`Error::Integrity`], so it
+/// is retried with backoff like a failed fetch; one off the tick/lot grid
+/// is not retried, nor is one with a level that doesn't parse
+/// ([`Error::Decode`]).
+async fn bootstrap(
+    pair: &str,
+    config: &StreamConfig,
+    filters: &Option<SymbolFilters>,
+    provider: &dyn SnapshotProvider,
+    priority: SnapshotPriority,
+) -> Result<OrderBook, Error> {
+    let mut ob = OrderBook::new(pair);
+    ob.depth = config.snapshot_depth();
+    ob.set_filters(filters.clone());
+    let snapshot = provider.snapshot(&ob.symbol, ob.depth, priority).await?;
+    if let Some([price, qty]) = snapshot.invalid_level() {
+        return Err(Error::Decode {
+            symbol: Some(pair.to_string()),
+            source: serde::de::Error::custom(format!("invalid level [{price:?}, {qty:?}]")),
+        });
+    }
+    let violation = ob.check_snapshot_grid(&snapshot);
+    ob.from_snapshot(&snapshot);
+    if let Some(violation) = violation.or_else(|| ob.check_integrity(config.min_book_levels())) {
+        telemetry::resync(pair, ResyncCause::Integrity(violation));
+        return Err(Error::Integrity {
+            symbol: pair.to_string(),
+            violation,
+        });
+    }
+    Ok(ob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ob_manager::snapshot_provider::SnapshotFn;
+    use crate::retry::RetryPolicy;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn unavailable() -> Error {
+        Error::Http {
+            symbol: Some("BTCUSDT".to_string()),
+            status: 503,
+            code: None,
+            message: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn snapshot_retries_stop_at_max_attempts() {
+        let escalations = Arc::new(Mutex::new(Vec::new()));
+        let seen = escalations.clone();
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_secs(1))
+            .jitter(0.0)
+            .max_attempts(Some(3))
+            .escalate_after(2)
+            .on_escalate(move |e| seen.lock().unwrap().push((e.attempts, e.gave_up)));
+        let config = StreamConfig::builder(["BTCUSDT"]).snapshot_retry(policy).build().unwrap();
+        let calls = AtomicU32::new(0);
+        let provider = SnapshotFn(|_symbol: &str, _limit: u16| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(unavailable())
+        });
+        let (status_tx, status_rx) = watch::channel(FeedHealth::new());
+
+        let started = tokio::time::Instant::now();
+        let result = bootstrap_with_retry(
+            "BTCUSDT",
+            &config,
+            &mut None,
+            false,
+            &provider,
+            SnapshotPriority::Initial,
+            &status_tx,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Http { status: 503, .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        // 1s then 2s between the three attempts.
+        assert_eq!(started.elapsed(), Duration::from_secs(3));
+        assert_eq!(*escalations.lock().unwrap(), [(2, false), (3, true)]);
+        assert!(matches!(
+            status_rx.borrow().status,
+            FeedStatus::Degraded { attempts: 2, .. }
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn non_retryable_snapshot_error_is_not_retried() {
+        let config = StreamConfig::builder(["BTCUSDT"]).build().unwrap();
+        let calls = AtomicU32::new(0);
+        let provider = SnapshotFn(|symbol: &str, _limit: u16| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(Error::UnknownSymbol {
+                symbol: symbol.to_string(),
+                status: None,
+            })
+        });
+        let (status_tx, _) = watch::channel(FeedHealth::new());
+
+        let result = bootstrap_with_retry(
+            "BTCUSDT",
+            &config,
+            &mut None,
+            false,
+            &provider,
+            SnapshotPriority::Initial,
+            &status_tx,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::UnknownSymbol { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
     }
-    ob_streams
 }
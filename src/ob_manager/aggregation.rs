@@ -0,0 +1,164 @@
+use crate::ob_manager::order_book::{price_to_f64, OrderBook, Price, Qty};
+
+// Absorbs f64 noise such as 0.5678 / 0.0001 = 5677.999... before floor/ceil.
+const EPS: f64 = 1e-9;
+
+/// Bucket width for [`OrderBook::aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceBucket {
+    /// Fixed price step on an absolute grid, e.g. `0.01` or `10.0`.
+    Step(f64),
+    /// Fixed distance from mid, in basis points of mid.
+    Bps(f64),
+}
+
+/// One aggregated price bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregatedLevel {
+    /// Bucket price: the lower edge for bids, the upper edge for asks.
+    pub price: f64,
+    /// Total quantity of the levels in the bucket.
+    pub qty: Qty,
+    /// Number of raw levels merged into the bucket.
+    pub levels: usize,
+}
+
+/// Book aggregated into price buckets, both sides best first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AggregatedBook {
+    pub bids: Vec<AggregatedLevel>,
+    pub asks: Vec<AggregatedLevel>,
+}
+
+impl OrderBook {
+    /// Groups levels into price buckets.
+    ///
+    /// Bids are rounded down and asks up, so each bucket's price is never
+    /// better than any level in it and quoted depth is never overstated.
+    /// Returns `None` for a non-positive step, or for [`PriceBucket::Bps`]
+    /// when either side is empty and there is no mid.
+    ///
+    /// ```
+    /// use binance_stream_handler::{OrderBook, PriceBucket};
+    ///
+    /// let mut book = OrderBook::new("BTCUSDT");
+    /// for (p, q) in [("100.4", "1"), ("100.9", "2"), ("99.7", "3")] {
+    ///     book.bids.insert(p.parse().unwrap(), q.parse().unwrap());
+    /// }
+    /// book.asks.insert("101.2".parse().unwrap(), "1".parse().unwrap());
+    ///
+    /// let agg = book.aggregate(PriceBucket::Step(1.0)).unwrap();
+    /// assert_eq!(agg.bids[0].price, 100.0);
+    /// assert_eq!(agg.bids[0].levels, 2);
+    /// assert_eq!(agg.bids[1].price, 99.0);
+    /// assert_eq!(agg.asks[0].price, 102.0);
+    /// ```
+    pub fn aggregate(&self, bucket: PriceBucket) -> Option<AggregatedBook> {
+        let (origin, width) = match bucket {
+            PriceBucket::Step(step) => (0.0, step),
+            PriceBucket::Bps(bps) => {
+                let mid = self.mid()?;
+                (mid, mid * bps / 10_000.0)
+            }
+        };
+        if width.is_nan() || width <= 0.0 {
+            return None;
+        }
+
+        let bid_edge = |p: f64| origin + ((p - origin) / width + EPS).floor() * width;
+        let ask_edge = |p: f64| origin + ((p - origin) / width - EPS).ceil() * width;
+
+        Some(AggregatedBook {
+            bids: merge(self.bids_desc(), bid_edge),
+            asks: merge(self.asks_asc(), ask_edge),
+        })
+    }
+}
+
+/// Merges best-first levels into buckets; consecutive levels share a bucket
+/// exactly when they map to the same edge.
+fn merge(
+    levels: impl Iterator<Item = (Price, Qty)>,
+    edge: impl Fn(f64) -> f64,
+) -> Vec<AggregatedLevel> {
+    let mut out: Vec<AggregatedLevel> = Vec::new();
+    for (p, q) in levels {
+        let price = edge(price_to_f64(p));
+        match out.last_mut() {
+            Some(last) if last.price == price => {
+                last.qty += q;
+                last.levels += 1;
+            }
+            _ => out.push(AggregatedLevel {
+                price,
+                qty: q,
+                levels: 1,
+            }),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> OrderBook {
+        let mut book = OrderBook::new("BTCUSDT");
+        for (p, q) in bids {
+            book.bids.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        for (p, q) in asks {
+            book.asks.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        book
+    }
+
+    fn prices(levels: &[AggregatedLevel]) -> Vec<f64> {
+        levels.iter().map(|l| l.price).collect()
+    }
+
+    #[test]
+    fn bids_round_down_and_asks_round_up() {
+        let book = book(
+            &[("100.04", "1"), ("100.01", "2"), ("99.99", "1")],
+            &[("100.11", "1"), ("100.19", "3"), ("100.21", "1")],
+        );
+        let agg = book.aggregate(PriceBucket::Step(0.1)).unwrap();
+        assert_eq!(prices(&agg.bids), [100.0, 99.9]);
+        assert_eq!(agg.bids[0].qty, "3".parse::<Qty>().unwrap());
+        assert_eq!(agg.bids[0].levels, 2);
+        // Roughly 100.2 and 100.3; the edges carry f64 noise from the step.
+        let asks = prices(&agg.asks);
+        assert_eq!(asks.len(), 2);
+        assert!((asks[0] - 100.2).abs() < 1e-9 && (asks[1] - 100.3).abs() < 1e-9);
+        assert_eq!(agg.asks[0].levels, 2);
+    }
+
+    #[test]
+    fn prices_on_an_edge_stay_in_their_own_bucket() {
+        let book = book(&[("0.5678", "1")], &[("0.5679", "1")]);
+        let agg = book.aggregate(PriceBucket::Step(0.0001)).unwrap();
+        assert!((agg.bids[0].price - 0.5678).abs() < 1e-12);
+        assert!((agg.asks[0].price - 0.5679).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bps_buckets_are_anchored_at_mid() {
+        let book = book(&[("99", "1"), ("98.5", "1")], &[("101", "1")]);
+        // mid 100, 100 bps = 1.0 wide.
+        let agg = book.aggregate(PriceBucket::Bps(100.0)).unwrap();
+        assert_eq!(prices(&agg.bids), [99.0, 98.0]);
+        assert_eq!(prices(&agg.asks), [101.0]);
+    }
+
+    #[test]
+    fn invalid_buckets_are_rejected() {
+        let two_sided = book(&[("99", "1")], &[("101", "1")]);
+        assert_eq!(two_sided.aggregate(PriceBucket::Step(0.0)), None);
+        assert_eq!(two_sided.aggregate(PriceBucket::Step(f64::NAN)), None);
+        let one_sided = book(&[("99", "1")], &[]);
+        assert_eq!(one_sided.aggregate(PriceBucket::Bps(10.0)), None);
+        assert!(one_sided.aggregate(PriceBucket::Step(1.0)).is_some());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::ob_manager::events::Bbo;
+use crate::ob_manager::order_book::{price_to_f64, qty_to_f64, OrderBook, Price, Qty};
+
+/// Top-of-book and depth queries.
+///
+/// Ratios (mid, spread in bps, microprice, imbalance) are returned as `f64`;
+/// prices and quantities keep the book's own [`Price`]/[`Qty`] types.
+impl OrderBook {
+    /// Highest bid as `(price, qty)`.
+    pub fn best_bid(&self) -> Option<(Price, Qty)> {
+        self.bids.iter().next_back().map(|(p, q)| (*p, *q))
+    }
+
+    /// Lowest ask as `(price, qty)`.
+    pub fn best_ask(&self) -> Option<(Price, Qty)> {
+        self.asks.iter().next().map(|(p, q)| (*p, *q))
+    }
+
+    /// Best bid and best ask together.
+    pub fn bbo(&self) -> Bbo {
+        Bbo {
+            bid: self.best_bid(),
+            ask: self.best_ask(),
+        }
+    }
+
+    /// Bid levels from best (highest) to worst.
+    pub fn bids_desc(&self) -> impl DoubleEndedIterator<Item = (Price, Qty)> + '_ {
+        self.bids.iter().rev().map(|(p, q)| (*p, *q))
+    }
+
+    /// Ask levels from best (lowest) to worst.
+    pub fn asks_asc(&self) -> impl DoubleEndedIterator<Item = (Price, Qty)> + '_ {
+        self.asks.iter().map(|(p, q)| (*p, *q))
+    }
+
+    /// `(best_bid + best_ask) / 2`, if both sides are present.
+    pub fn mid(&self) -> Option<f64> {
+        let (bid, ask) = self.top_prices()?;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// `best_ask - best_bid`; negative if the book is crossed.
+    pub fn spread(&self) -> Option<Price> {
+        let (bid, _) = self.best_bid()?;
+        let (ask, _) = self.best_ask()?;
+        Some(ask - bid)
+    }
+
+    /// Spread relative to mid, in basis points.
+    pub fn spread_bps(&self) -> Option<f64> {
+        let (bid, ask) = self.top_prices()?;
+        let mid = (bid + ask) / 2.0;
+        (mid > 0.0).then(|| (ask - bid) / mid * 10_000.0)
+    }
+
+    /// Size-weighted mid: leans towards the side with less quantity at the top.
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid, bid_qty) = self.best_bid()?;
+        let (ask, ask_qty) = self.best_ask()?;
+        let (bid_qty, ask_qty) = (qty_to_f64(bid_qty), qty_to_f64(ask_qty));
+        let total = bid_qty + ask_qty;
+        (total > 0.0)
+            .then(|| (price_to_f64(bid) * ask_qty + price_to_f64(ask) * bid_qty) / total)
+    }
+
+    /// `(bid_qty - ask_qty) / (bid_qty + ask_qty)` over the top `levels` of
+    /// each side, in `[-1, 1]`. `None` if both sides are empty.
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid_qty: f64 = self.bids_desc().take(levels).map(|(_, q)| qty_to_f64(q)).sum();
+        let ask_qty: f64 = self.asks_asc().take(levels).map(|(_, q)| qty_to_f64(q)).sum();
+        let total = bid_qty + ask_qty;
+        (total > 0.0).then(|| (bid_qty - ask_qty) / total)
+    }
+
+    /// Total bid quantity at prices `>= price`.
+    pub fn bid_depth_to(&self, price: Price) -> Qty {
+        self.bids
+            .range(price..)
+            .fold(Qty::default(), |acc, (_, q)| acc + *q)
+    }
+
+    /// Total ask quantity at prices `<= price`.
+    pub fn ask_depth_to(&self, price: Price) -> Qty {
+        self.asks
+            .range(..=price)
+            .fold(Qty::default(), |acc, (_, q)| acc + *q)
+    }
+
+    fn top_prices(&self) -> Option<(f64, f64)> {
+        let (bid, _) = self.best_bid()?;
+        let (ask, _) = self.best_ask()?;
+        Some((price_to_f64(bid), price_to_f64(ask)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> OrderBook {
+        let mut book = OrderBook::new("BTCUSDT");
+        for (p, q) in bids {
+            book.bids.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        for (p, q) in asks {
+            book.asks.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        book
+    }
+
+    fn px(s: &str) -> Price {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn top_of_book_and_spread() {
+        let book = book(&[("99", "1"), ("100", "3")], &[("101", "1"), ("102", "5")]);
+        assert_eq!(book.best_bid().map(|(p, _)| p), Some(px("100")));
+        assert_eq!(book.best_ask().map(|(p, _)| p), Some(px("101")));
+        assert_eq!(book.mid(), Some(100.5));
+        assert_eq!(book.spread(), Some(px("1")));
+        assert!((book.spread_bps().unwrap() - 1.0 / 100.5 * 10_000.0).abs() < 1e-9);
+        let bids: Vec<Price> = book.bids_desc().map(|(p, _)| p).collect();
+        assert_eq!(bids, [px("100"), px("99")]);
+    }
+
+    #[test]
+    fn microprice_leans_towards_the_thinner_side() {
+        // 3 bid vs 1 ask at the top: price pressure is up, towards the ask.
+        let book = book(&[("100", "3")], &[("101", "1")]);
+        assert_eq!(book.microprice(), Some(100.75));
+    }
+
+    #[test]
+    fn imbalance_covers_the_requested_levels() {
+        let book = book(&[("99", "5"), ("100", "3")], &[("101", "1"), ("102", "1")]);
+        assert_eq!(book.imbalance(1), Some(0.5));
+        assert_eq!(book.imbalance(2), Some(0.6));
+        assert_eq!(OrderBook::new("BTCUSDT").imbalance(5), None);
+    }
+
+    #[test]
+    fn depth_to_includes_the_bound() {
+        let book = book(
+            &[("98", "1"), ("99", "2"), ("100", "3")],
+            &[("101", "1"), ("102", "2"), ("103", "4")],
+        );
+        assert_eq!(book.bid_depth_to(px("99")), "5".parse::<Qty>().unwrap());
+        assert_eq!(book.ask_depth_to(px("102")), "3".parse::<Qty>().unwrap());
+    }
+
+    #[test]
+    fn one_sided_book_has_no_mid() {
+        let book = book(&[("100", "1")], &[]);
+        assert_eq!(book.mid(), None);
+        assert_eq!(book.spread(), None);
+        assert_eq!(book.microprice(), None);
+        assert_eq!(book.bbo().ask, None);
+    }
+}
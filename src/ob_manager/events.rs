@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use crate::ob_manager::order_book::{OrderBook, Price, Qty};
+
+/// Side of a book level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// One level changed by an applied update.
+///
+/// Quantities are absolute; `0` means the level did not exist (`old_qty`) or
+/// was removed (`new_qty`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelChange {
+    pub side: BookSide,
+    pub price: Price,
+    pub old_qty: Qty,
+    pub new_qty: Qty,
+}
+
+/// Levels changed by one applied `depthUpdate`, with its sequence ids and times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookDelta {
+    pub symbol: String,
+    /// `U` of the update.
+    pub first_update_id: u64,
+    /// `u` of the update; equals the book's `last_u` once applied.
+    pub final_update_id: u64,
+    /// `E`, event time (ms).
+    pub event_time: u64,
+    /// `T`, transaction time (ms), futures only.
+    pub transaction_time: Option<u64>,
+    /// Levels whose quantity actually changed, bids first.
+    pub changes: Vec<LevelChange>,
+}
+
+/// Event on a symbol's book event stream, see [`StreamHandle::book_events`].
+///
+/// Every change to the published book appears here in order, so replaying
+/// the events reproduces each intermediate book the `watch` channel may have
+/// conflated away.
+///
+/// [`StreamHandle::book_events`]: crate::StreamHandle::book_events
+#[derive(Debug, Clone)]
+pub enum BookEvent {
+    /// One update was applied.
+    Delta(Arc<BookDelta>),
+    /// The book was rebuilt from scratch — the initial snapshot, a resync,
+    /// or a partial-depth message — and earlier deltas no longer apply.
+    /// Carries the new book (an O(1) clone).
+    Reset(OrderBook),
+}
+
+/// Best bid and offer as `(price, qty)`; a side is `None` while empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bbo {
+    pub bid: Option<(Price, Qty)>,
+    pub ask: Option<(Price, Qty)>,
+}
+
+/// The top of a book changed in price or quantity, see
+/// [`StreamHandle::bbo_events`].
+///
+/// [`StreamHandle::bbo_events`]: crate::StreamHandle::bbo_events
+#[derive(Debug, Clone, PartialEq)]
+pub struct BboEvent {
+    pub symbol: String,
+    pub prev: Bbo,
+    pub new: Bbo,
+    /// Book's `last_u` after the change (the snapshot id after a rebuild).
+    pub update_id: u64,
+    /// `E` of the update that moved the top; `None` after a REST snapshot.
+    pub event_time: Option<u64>,
+}
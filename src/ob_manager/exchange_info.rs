@@ -0,0 +1,240 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tracing::debug;
+
+use crate::config::{Market, StreamConfig};
+use crate::error::Error;
+
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInfo {
+    symbol: String,
+    status: Option<String>,          // spot / USD-M
+    contract_status: Option<String>, // COIN-M
+    filters: Vec<serde_json::Value>,
+}
+
+/// A grid step such as a tick or lot size, kept as an exact decimal.
+#[derive(Debug, Clone, PartialEq)]
+struct GridStep {
+    units: i128, // step expressed in 10^-scale units
+    scale: u32,
+}
+
+impl GridStep {
+    fn parse(s: &str) -> Option<Self> {
+        let (units, scale) = parse_fixed(s)?;
+        (units > 0).then_some(Self { units, scale })
+    }
+
+    /// Number of whole steps in `value`, or `None` if it is off-grid.
+    fn steps(&self, value: &str) -> Option<i128> {
+        let (units, scale) = parse_fixed(value)?;
+        if scale > self.scale {
+            return None;
+        }
+        let units = units * 10i128.pow(self.scale - scale);
+        (units % self.units == 0).then_some(units / self.units)
+    }
+
+    fn as_f64(&self) -> f64 {
+        self.units as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+/// `"0.01000"` → `(1, 2)`: mantissa and number of decimals, trailing zeros trimmed.
+fn parse_fixed(s: &str) -> Option<(i128, u32)> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let frac = frac.trim_end_matches('0');
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    let digits = format!("{int}{frac}");
+    let units = digits.parse::<i128>().ok()?;
+    Some((units, frac.len() as u32))
+}
+
+/// Tick and lot size of one symbol, from `exchangeInfo`.
+///
+/// Conversions are done on the exact Binance strings, so a price is either a
+/// whole number of ticks or rejected as off-grid.
+///
+/// ```
+/// use binance_stream_handler::SymbolFilters;
+///
+/// let f = SymbolFilters::new("ADAUSDT", "0.00010", "1").unwrap();
+/// assert_eq!(f.price_to_ticks("0.56780"), Some(5678));
+/// assert_eq!(f.price_to_ticks("0.56785"), None);
+/// assert_eq!(f.qty_to_lots("125.000"), Some(125));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolFilters {
+    pub symbol: String,
+    /// `PRICE_FILTER.tickSize`, as sent by Binance.
+    pub tick_size: String,
+    /// `LOT_SIZE.stepSize`, as sent by Binance.
+    pub step_size: String,
+    tick: GridStep,
+    step: GridStep,
+}
+
+impl SymbolFilters {
+    pub fn new(symbol: &str, tick_size: &str, step_size: &str) -> Option<Self> {
+        Some(Self {
+            symbol: symbol.to_ascii_uppercase(),
+            tick_size: tick_size.to_string(),
+            step_size: step_size.to_string(),
+            tick: GridStep::parse(tick_size)?,
+            step: GridStep::parse(step_size)?,
+        })
+    }
+
+    /// Price in whole ticks, or `None` if `price` is not on the tick grid.
+    pub fn price_to_ticks(&self, price: &str) -> Option<i64> {
+        self.tick.steps(price).and_then(|t| i64::try_from(t).ok())
+    }
+
+    /// Quantity in whole lots, or `None` if `qty` is not on the lot grid.
+    pub fn qty_to_lots(&self, qty: &str) -> Option<u64> {
+        self.step.steps(qty).and_then(|l| u64::try_from(l).ok())
+    }
+
+    pub fn ticks_to_price(&self, ticks: i64) -> f64 {
+        ticks as f64 * self.tick.as_f64()
+    }
+
+    pub fn lots_to_qty(&self, lots: u64) -> f64 {
+        lots as f64 * self.step.as_f64()
+    }
+}
+
+/// Client for [`fetch_symbol_filters`], built on first use.
+static HTTP: OnceLock<Client> = OnceLock::new();
+
+/// Fetches `exchangeInfo` and returns the filters of every requested symbol.
+///
+/// On spot only the requested symbols are downloaded; the futures endpoints
+/// take no symbol filter and always return the full list.
+///
+/// Fails with [`Error::UnknownSymbol`] if a symbol is missing or not trading.
+///
+/// The request is not charged to any weight budget; the pipeline loads
+/// filters through [`SnapshotProvider::symbol_filters`] instead, which for
+/// [`SnapshotClient`] shares its client and budget with the snapshots.
+///
+/// [`SnapshotProvider::symbol_filters`]: crate::SnapshotProvider::symbol_filters
+/// [`SnapshotClient`]: crate::SnapshotClient
+pub async fn fetch_symbol_filters(
+    config: &StreamConfig,
+    symbols: &[String],
+) -> Result<HashMap<String, SymbolFilters>, Error> {
+    let http = match HTTP.get() {
+        Some(http) => http,
+        None => {
+            let http = Client::builder()
+                .user_agent("binance-stream-handler/0.1")
+                .build()
+                .map_err(|source| Error::Transport {
+                    symbol: None,
+                    source,
+                })?;
+            HTTP.get_or_init(|| http)
+        }
+    };
+    request_symbol_filters(http, config.rest_base_url(), config.market(), symbols, |_| {}).await
+}
+
+/// [`fetch_symbol_filters`] on `http`, with `observe` seeing the response
+/// before its body is read.
+pub(crate) async fn request_symbol_filters(
+    http: &Client,
+    rest_base_url: &str,
+    market: Market,
+    symbols: &[String],
+    observe: impl FnOnce(&reqwest::Response),
+) -> Result<HashMap<String, SymbolFilters>, Error> {
+    let mut url = format!("{rest_base_url}{}", market.exchange_info_path());
+    if market == Market::Spot {
+        // Symbols are validated upper-case alphanumerics; only the JSON
+        // array punctuation needs escaping.
+        match symbols {
+            [] => {}
+            [sym] => url.push_str(&format!("?symbol={sym}")),
+            _ => url.push_str(&format!("?symbols=%5B%22{}%22%5D", symbols.join("%22%2C%22"))),
+        }
+    }
+    let transport = |source| Error::Transport {
+        symbol: None,
+        source,
+    };
+
+    let resp = http.get(&url).send().await.map_err(transport)?;
+    observe(&resp);
+    let status = resp.status();
+    let body = resp.bytes().await.map_err(transport)?;
+    if !status.is_success() {
+        let error = Error::from_response(None, status, None, &body);
+        // Spot rejects the whole request when one symbol is invalid.
+        if let ([sym], Some(-1121)) = (symbols, error.code()) {
+            return Err(Error::UnknownSymbol {
+                symbol: sym.clone(),
+                status: None,
+            });
+        }
+        return Err(error);
+    }
+    let info: ExchangeInfo = serde_json::from_slice(&body).map_err(|source| Error::Decode {
+        symbol: None,
+        source,
+    })?;
+
+    let mut by_symbol: HashMap<String, SymbolInfo> = info
+        .symbols
+        .into_iter()
+        .map(|s| (s.symbol.clone(), s))
+        .collect();
+
+    let mut out = HashMap::new();
+    for sym in symbols {
+        let Some(entry) = by_symbol.remove(sym) else {
+            return Err(Error::UnknownSymbol {
+                symbol: sym.clone(),
+                status: None,
+            });
+        };
+        let status = entry.status.clone().or(entry.contract_status.clone());
+        if status.as_deref() != Some("TRADING") {
+            return Err(Error::UnknownSymbol {
+                symbol: sym.clone(),
+                status,
+            });
+        }
+
+        let filter_value = |kind: &str, key: &str| {
+            entry
+                .filters
+                .iter()
+                .find(|f| f["filterType"] == kind)
+                .and_then(|f| f[key].as_str())
+                .map(str::to_string)
+        };
+        let filters = filter_value("PRICE_FILTER", "tickSize")
+            .zip(filter_value("LOT_SIZE", "stepSize"))
+            .and_then(|(tick, step)| SymbolFilters::new(sym, &tick, &step))
+            .ok_or_else(|| Error::UnknownSymbol {
+                symbol: sym.clone(),
+                status: Some("missing PRICE_FILTER/LOT_SIZE".to_string()),
+            })?;
+
+        debug!(symbol=%sym, tick=%filters.tick_size, step=%filters.step_size, "Symbol filters loaded");
+        out.insert(sym.clone(), filters);
+    }
+    Ok(out)
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::ob_manager::events::BookSide;
+use crate::ob_manager::order_book::{DepthUpdate, Levels, OrderBook, Price, Qty};
+
+/// A book state that can't be right, found by [`OrderBook::check_integrity`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegrityViolation {
+    /// Best bid above best ask.
+    Crossed { bid: Price, ask: Price },
+    /// Best bid equal to best ask.
+    Locked { price: Price },
+    /// A stored quantity that is zero, negative, NaN or infinite.
+    InvalidQty {
+        side: BookSide,
+        price: Price,
+        qty: Qty,
+    },
+    /// A side holds fewer levels than [`StreamConfig::min_book_levels`].
+    ///
+    /// [`StreamConfig::min_book_levels`]: crate::StreamConfig::min_book_levels
+    DepthCollapse { side: BookSide, levels: usize },
+    /// A snapshot level off the symbol's tick/lot grid, see
+    /// [`StreamConfig::tick_ladder`].
+    ///
+    /// [`StreamConfig::tick_ladder`]: crate::StreamConfig::tick_ladder
+    OffGrid {
+        side: BookSide,
+        price: Price,
+        qty: Qty,
+    },
+}
+
+impl fmt::Display for IntegrityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityViolation::Crossed { bid, ask } => {
+                write!(f, "crossed book: best bid {bid} > best ask {ask}")
+            }
+            IntegrityViolation::Locked { price } => write!(f, "locked book at {price}"),
+            IntegrityViolation::InvalidQty { side, price, qty } => {
+                write!(f, "invalid {side:?} quantity {qty} at {price}")
+            }
+            IntegrityViolation::DepthCollapse { side, levels } => {
+                write!(f, "{side:?} side collapsed to {levels} levels")
+            }
+            IntegrityViolation::OffGrid { side, price, qty } => {
+                write!(f, "{side:?} level {qty} at {price} is off the tick/lot grid")
+            }
+        }
+    }
+}
+
+impl OrderBook {
+    /// Full check, for a freshly loaded snapshot: top of book, every
+    /// quantity, and at least `min_levels` levels per side.
+    pub fn check_integrity(&self, min_levels: usize) -> Option<IntegrityViolation> {
+        self.check_top(min_levels).or_else(|| {
+            let side_bad = |side, levels: &Levels| {
+                levels.iter().find(|(_, q)| !valid_qty(**q)).map(|(p, q)| {
+                    IntegrityViolation::InvalidQty {
+                        side,
+                        price: *p,
+                        qty: *q,
+                    }
+                })
+            };
+            side_bad(BookSide::Bid, &self.bids).or_else(|| side_bad(BookSide::Ask, &self.asks))
+        })
+    }
+
+    /// Cheap check after applying `ev`: top of book, depth, and only the
+    /// quantities `ev` touched.
+    pub fn check_update_integrity(
+        &self,
+        ev: &DepthUpdate,
+        min_levels: usize,
+    ) -> Option<IntegrityViolation> {
+        self.check_top(min_levels).or_else(|| {
+            let touched = |side, levels: &Levels, updates: &[[String; 2]]| {
+                updates.iter().find_map(|[p, q]| {
+                    let (price, qty) = Self::parse_level(p, q)?;
+                    let stored = levels.get(&price)?;
+                    (!valid_qty(*stored)).then_some(IntegrityViolation::InvalidQty {
+                        side,
+                        price,
+                        qty,
+                    })
+                })
+            };
+            touched(BookSide::Bid, &self.bids, &ev.b)
+                .or_else(|| touched(BookSide::Ask, &self.asks, &ev.a))
+        })
+    }
+
+    fn check_top(&self, min_levels: usize) -> Option<IntegrityViolation> {
+        for (side, levels) in [(BookSide::Bid, self.bids.len()), (BookSide::Ask, self.asks.len())] {
+            if levels < min_levels {
+                return Some(IntegrityViolation::DepthCollapse { side, levels });
+            }
+        }
+        let (bid, _) = self.best_bid()?;
+        let (ask, _) = self.best_ask()?;
+        if bid > ask {
+            Some(IntegrityViolation::Crossed { bid, ask })
+        } else if bid == ask {
+            Some(IntegrityViolation::Locked { price: bid })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+fn valid_qty(q: Qty) -> bool {
+    q.is_finite() && q > 0.0
+}
+
+#[cfg(feature = "decimal")]
+fn valid_qty(q: Qty) -> bool {
+    q > Qty::ZERO
+}
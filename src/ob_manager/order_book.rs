@@ -1,54 +1,266 @@
+#[cfg(not(feature = "decimal"))]
 use ordered_float::OrderedFloat as OF;
-use reqwest::Client;
 use serde::Deserialize;
-use std::collections::BTreeMap;
-use tracing::{debug, error, info, trace, warn};
+use im::OrdMap;
+use std::sync::Arc;
+use tracing::{debug, warn};
 
-type Price = OF<f64>;
-type Qty = f64;
+use crate::latency::ReceiveStamp;
+use crate::ob_manager::events::{BookSide, LevelChange};
+use crate::ob_manager::exchange_info::SymbolFilters;
+use crate::ob_manager::integrity::IntegrityViolation;
 
+/// Price key of a book level.
+///
+/// `OrderedFloat<f64>` by default; with the `decimal` feature, an exact
+/// `rust_decimal::Decimal` so distinct Binance price strings never collapse
+/// into one key and removals on qty `"0"` always hit the right level.
+#[cfg(not(feature = "decimal"))]
+pub type Price = OF<f64>;
+/// Quantity of a book level (`f64`, or `Decimal` with the `decimal` feature).
+#[cfg(not(feature = "decimal"))]
+pub type Qty = f64;
+
+#[cfg(feature = "decimal")]
+pub type Price = rust_decimal::Decimal;
+#[cfg(feature = "decimal")]
+pub type Qty = rust_decimal::Decimal;
+
+/// One side of the book: a persistent sorted map, price ascending.
+///
+/// Clones share structure, so cloning a book is O(1) and an update copies
+/// only the O(log n) nodes on its path.
+pub type Levels = OrdMap<Price, Qty>;
+
+/// Lossy `f64` view of a [`Price`], for analytics.
+#[cfg(not(feature = "decimal"))]
+pub fn price_to_f64(p: Price) -> f64 {
+    p.into_inner()
+}
+/// Lossy `f64` view of a [`Qty`], for analytics.
+#[cfg(not(feature = "decimal"))]
+pub fn qty_to_f64(q: Qty) -> f64 {
+    q
+}
+
+#[cfg(feature = "decimal")]
+pub fn price_to_f64(p: Price) -> f64 {
+    rust_decimal::prelude::ToPrimitive::to_f64(&p).unwrap_or(f64::NAN)
+}
+#[cfg(feature = "decimal")]
+pub fn qty_to_f64(q: Qty) -> f64 {
+    rust_decimal::prelude::ToPrimitive::to_f64(&q).unwrap_or(f64::NAN)
+}
+
+/// One `depthUpdate` event.
+///
+/// Futures events carry `T` and `pu`; spot events have neither, and are
+/// sequenced by `U == previous u + 1` instead. COIN-M events also carry the
+/// underlying pair in `ps`. Unknown payload fields are ignored.
+///
+/// Spot partial-depth messages (`lastUpdateId`/`bids`/`asks`) decode into
+/// the same struct with `U`, `E` and `s` left empty; the router fills `s`
+/// from the stream name. A diff event missing any of `e`, `E`, `s` or `U`,
+/// or with a price or quantity that isn't a number, is rejected.
 #[allow(non_snake_case)]
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(try_from = "RawDepthUpdate")]
 pub struct DepthUpdate {
     pub e: String,           // Event type: "depthUpdate"
     pub E: u64,              // Event time
-    pub T: u64,              // Transaction time
-    pub s: String,           // Symbol
+    pub T: Option<u64>,      // Transaction time (futures only)
+    pub s: String,           // Symbol, e.g. "BTCUSD_PERP" on COIN-M
+    pub ps: Option<String>,  // Pair, COIN-M only (e.g. "BTCUSD")
     pub U: u64,              // First update ID in event
     pub u: u64,              // Final update ID in event
-    pub pu: u64,             // Final update Id in last stream(ie `u` in last stream)
+    pub pu: Option<u64>,     // Final update Id in last stream(ie `u` in last stream), futures only
     pub b: Vec<[String; 2]>, // bids updates [price, qty]
     pub a: Vec<[String; 2]>, // asks updates
     pub channel_load: Option<usize>,
+    /// When the WS reader received the message; not part of the payload.
+    pub received: Option<ReceiveStamp>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+/// Wire form of [`DepthUpdate`]: a diff/futures event or a spot partial.
+#[allow(non_snake_case)]
+#[derive(Deserialize)]
+struct RawDepthUpdate {
+    e: Option<String>,
+    E: Option<u64>,
+    T: Option<u64>,
+    s: Option<String>,
+    ps: Option<String>,
+    U: Option<u64>,
+    u: Option<u64>,
+    pu: Option<u64>,
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: Option<u64>,
+    #[serde(alias = "bids", deserialize_with = "levels")]
+    b: Vec<[String; 2]>,
+    #[serde(alias = "asks", deserialize_with = "levels")]
+    a: Vec<[String; 2]>,
+    channel_load: Option<usize>,
+}
+
+/// `[price, qty]` levels, each checked to parse as [`Price`] and [`Qty`].
+fn levels<'de, D>(deserializer: D) -> Result<Vec<[String; 2]>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let levels = Vec::<[String; 2]>::deserialize(deserializer)?;
+    match invalid_level(&levels) {
+        Some([p, q]) => Err(serde::de::Error::custom(format!("invalid level [{p:?}, {q:?}]"))),
+        None => Ok(levels),
+    }
+}
+
+/// First level whose price or quantity doesn't parse.
+pub(crate) fn invalid_level(levels: &[[String; 2]]) -> Option<&[String; 2]> {
+    levels
+        .iter()
+        .find(|[p, q]| OrderBook::parse_level(p, q).is_none())
+}
+
+impl TryFrom<RawDepthUpdate> for DepthUpdate {
+    type Error = String;
+
+    #[allow(non_snake_case)]
+    fn try_from(raw: RawDepthUpdate) -> Result<Self, Self::Error> {
+        let (e, E, s, U, u) = match (raw.e, raw.last_update_id) {
+            // Spot partial depth: just `lastUpdateId`, `bids` and `asks`.
+            (None, Some(last_update_id)) => (String::new(), 0, String::new(), 0, last_update_id),
+            (Some(e), _) => {
+                let missing = |field: &str| format!("depth event without `{field}`");
+                (
+                    e,
+                    raw.E.ok_or_else(|| missing("E"))?,
+                    raw.s.ok_or_else(|| missing("s"))?,
+                    raw.U.ok_or_else(|| missing("U"))?,
+                    raw.u.ok_or_else(|| missing("u"))?,
+                )
+            }
+            (None, None) => return Err("neither a depth event nor a partial depth".into()),
+        };
+        Ok(Self {
+            e,
+            E,
+            T: raw.T,
+            s,
+            ps: raw.ps,
+            U,
+            u,
+            pu: raw.pu,
+            b: raw.b,
+            a: raw.a,
+            channel_load: raw.channel_load,
+            received: None,
+        })
+    }
+}
+
+impl DepthUpdate {
+    /// The `u` of the event this one must directly follow: `pu` on futures,
+    /// `U - 1` on spot.
+    pub fn prev_u(&self) -> u64 {
+        self.pu.unwrap_or(self.U.saturating_sub(1))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 pub struct CombinedDepthUpdate {
     // e.g. "adausdt@depth@100ms"
     pub stream: String,
     pub data: DepthUpdate,
 }
 
+/// Why a book has to be rebuilt from a fresh snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResyncCause {
+    /// An update arrived before any snapshot was loaded.
+    NoSnapshot,
+    /// The first update after the snapshot did not bridge it.
+    MissedBridge,
+    /// `pu` (or `U - 1` on spot) skipped past the last applied `u`.
+    SequenceGap,
+    /// An update had a price or quantity off the tick/lot grid.
+    OffGrid,
+    /// The book failed its integrity check after an update or snapshot.
+    Integrity(IntegrityViolation),
+    /// The router connection delivering the symbol dropped.
+    ConnectionLost,
+}
+
+impl ResyncCause {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResyncCause::NoSnapshot => "no_snapshot",
+            ResyncCause::MissedBridge => "missed_bridge",
+            ResyncCause::SequenceGap => "sequence_gap",
+            ResyncCause::OffGrid => "off_grid",
+            ResyncCause::Integrity(_) => "integrity",
+            ResyncCause::ConnectionLost => "connection_lost",
+        }
+    }
+}
+
+#[allow(non_snake_case)]
 #[derive(Debug, Clone)]
 pub struct ResyncNeeded {
     pub symbol: String,
+    pub cause: ResyncCause,
     pub expected_pu: Option<u64>, // what we expected (prev u)
-    pub got_pu: u64,              // the pu we received
+    pub got_pu: u64,              // the pu we received (U - 1 on spot)
     pub got_U: u64,
     pub got_u: u64,               // the u we received
 }
 
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize)]
+/// A REST depth snapshot, as returned by the depth endpoint.
+///
+/// Decoding fails on a price or quantity that isn't a number.
+#[allow(non_snake_case, dead_code)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DepthSnapshot {
     #[serde(rename = "lastUpdateId")]
     last_update_id: u64,
-    E: u64,                 // event time (ms)
-    T: u64,                 // transaction time (ms)
+    E: Option<u64>,         // event time (ms), futures only
+    T: Option<u64>,         // transaction time (ms), futures only
+    #[serde(deserialize_with = "levels")]
     bids: Vec<[String; 2]>, // [price, qty]
+    #[serde(deserialize_with = "levels")]
     asks: Vec<[String; 2]>,
 }
 
+impl DepthSnapshot {
+    /// A snapshot built by hand, e.g. for a mock [`SnapshotProvider`].
+    ///
+    /// [`SnapshotProvider`]: crate::SnapshotProvider
+    pub fn new(last_update_id: u64, bids: Vec<[String; 2]>, asks: Vec<[String; 2]>) -> Self {
+        Self {
+            last_update_id,
+            E: None,
+            T: None,
+            bids,
+            asks,
+        }
+    }
+
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    /// First level whose price or quantity doesn't parse, e.g. in a snapshot
+    /// built with [`new`](Self::new).
+    pub(crate) fn invalid_level(&self) -> Option<&[String; 2]> {
+        invalid_level(&self.bids).or_else(|| invalid_level(&self.asks))
+    }
+
+    /// Keeps the best `limit` levels of each side.
+    pub(crate) fn truncate(&mut self, limit: usize) {
+        self.bids.truncate(limit);
+        self.asks.truncate(limit);
+    }
+}
+
 #[derive(Debug)]
 pub enum UpdateDecision<'a> {
     Drop,                   // ignore this event
@@ -56,6 +268,17 @@ pub enum UpdateDecision<'a> {
     Resync(ResyncNeeded),   // trigger re-snapshot
 }
 
+/// Book levels as integer counts: price in ticks → quantity in lots.
+///
+/// Kept alongside the `Price`/`Qty` maps when the book has [`SymbolFilters`];
+/// convert back with [`SymbolFilters::ticks_to_price`] and
+/// [`SymbolFilters::lots_to_qty`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickLadder {
+    pub bids: OrdMap<i64, u64>,
+    pub asks: OrdMap<i64, u64>,
+}
+
 /// A sorted Binance order book (bids descending by price, asks ascending).
 ///
 /// Values are **absolute quantities** (Binance-style). `last_u` and `snapshot_id`
@@ -63,61 +286,81 @@ pub enum UpdateDecision<'a> {
 ///
 /// Most users don't construct `OrderBook` directly—consume it via the
 /// `watch::Receiver<OrderBook>` returned by [`generate_orderbooks`].
+/// `rx.borrow().clone()` is O(1): both sides are persistent [`Levels`] maps
+/// and the filters are shared, so a clone never copies levels.
 #[derive(Debug, Clone)]
 pub struct OrderBook {
     pub symbol: String,
-    // Sorted by price ascending (wrapped so it implements Ord)
-    pub bids: BTreeMap<Price, Qty>,
-    pub asks: BTreeMap<Price, Qty>,
+    // Sorted by price ascending
+    pub bids: Levels,
+    pub asks: Levels,
     pub last_u: Option<u64>,
     pub snapshot_id: Option<u64>,
     pub depth: u16,
+    /// Tick and lot size, when loaded from `exchangeInfo`.
+    pub filters: Option<Arc<SymbolFilters>>,
+    /// Integer ladder, present whenever `filters` is.
+    pub ticks: Option<TickLadder>,
+    /// `E` of the last applied update (ms).
+    pub last_event_time: Option<u64>,
+    /// `T` of the last applied update (ms), futures only.
+    pub last_transaction_time: Option<u64>,
+    /// When the WS reader received the last applied update.
+    pub last_received: Option<ReceiveStamp>,
 }
 
 impl OrderBook {
     pub fn new(symbol: &str) -> Self {
         Self {
             symbol: symbol.to_ascii_uppercase(),
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
+            bids: Levels::new(),
+            asks: Levels::new(),
             last_u: None,
             snapshot_id: None,
             depth: 1000,
+            filters: None,
+            ticks: None,
+            last_event_time: None,
+            last_transaction_time: None,
+            last_received: None,
         }
     }
 
-    pub async fn init_ob(symbol: &str) -> Result<OrderBook, Box<dyn std::error::Error>> {
-        let mut ob = OrderBook::new(symbol);
-        let snapshot = ob.get_depth_snapshot(ob.depth).await?;
-        ob.from_snapshot(&snapshot);
-        Ok(ob)
+    /// Attaches tick/lot filters; the integer ladder is filled from the next
+    /// snapshot on.
+    pub fn set_filters(&mut self, filters: Option<SymbolFilters>) {
+        self.ticks = filters.as_ref().map(|_| TickLadder::default());
+        self.filters = filters.map(Arc::new);
     }
 
-    pub async fn resync_ob(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let new_snapshot = self.get_depth_snapshot(self.depth).await?;
-        self.from_snapshot(&new_snapshot);
-        Ok(())
+    /// First `[price, qty]` in `ev` that is not on the tick/lot grid, if any.
+    ///
+    /// Always `None` for books without filters.
+    pub fn off_grid_level<'a>(&self, ev: &'a DepthUpdate) -> Option<&'a [String; 2]> {
+        self.first_off_grid(&ev.b, &ev.a).map(|(_, level)| level)
     }
 
-    pub async fn get_depth_snapshot(
-        &self,
-        limit: u16,
-    ) -> Result<DepthSnapshot, Box<dyn std::error::Error>> {
-        let sym = self.symbol.to_ascii_uppercase();
-        let url = format!("https://fapi.binance.com/fapi/v1/depth?symbol={sym}&limit={limit}");
-
-        let client = Client::builder()
-            .user_agent("binance-stream-handler/0.1")
-            .build()?;
-
-        let resp = client.get(&url).send().await?;
-        if !resp.status().is_success() {
-            return Err(format!("Snapshot HTTP error: {}", resp.status()).into());
-        }
-
-        let snapshot: DepthSnapshot = resp.json().await?;
+    /// First level of `snap` that is not on the tick/lot grid, as a
+    /// violation. Always `None` for books without filters.
+    pub fn check_snapshot_grid(&self, snap: &DepthSnapshot) -> Option<IntegrityViolation> {
+        let (side, [p, q]) = self.first_off_grid(&snap.bids, &snap.asks)?;
+        let (price, qty) = Self::parse_level(p, q)?;
+        Some(IntegrityViolation::OffGrid { side, price, qty })
+    }
 
-        Ok(snapshot)
+    fn first_off_grid<'a>(
+        &self,
+        bids: &'a [[String; 2]],
+        asks: &'a [[String; 2]],
+    ) -> Option<(BookSide, &'a [String; 2])> {
+        let filters = self.filters.as_ref()?;
+        let on_grid = |[p, q]: &[String; 2]| {
+            filters.price_to_ticks(p).is_some() && filters.qty_to_lots(q).is_some()
+        };
+        let side = |side, levels: &'a [[String; 2]]| {
+            levels.iter().find(|l| !on_grid(l)).map(|l| (side, l))
+        };
+        side(BookSide::Bid, bids).or_else(|| side(BookSide::Ask, asks))
     }
 
     /// Build a sorted book directly from a REST snapshot.
@@ -128,50 +371,122 @@ impl OrderBook {
         self.snapshot_id = Some(snap.last_update_id);
 
         for [p, q] in &snap.bids {
-            let (p, q) = (Self::parse_f64(p), Self::parse_f64(q));
-            if q != 0.0 {
-                self.bids.insert(OF(p), q);
+            let Some((p, q)) = Self::parse_level(p, q) else {
+                warn!(symbol=%self.symbol, price=%p, qty=%q, "Unparsable level skipped");
+                continue;
+            };
+            if !Self::is_zero(q) {
+                self.bids.insert(p, q);
             }
         }
         for [p, q] in &snap.asks {
-            let (p, q) = (Self::parse_f64(p), Self::parse_f64(q));
-            if q != 0.0 {
-                self.asks.insert(OF(p), q);
+            let Some((p, q)) = Self::parse_level(p, q) else {
+                warn!(symbol=%self.symbol, price=%p, qty=%q, "Unparsable level skipped");
+                continue;
+            };
+            if !Self::is_zero(q) {
+                self.asks.insert(p, q);
             }
         }
+        self.rebuild_ticks(&snap.bids, &snap.asks);
     }
 
-    /// Apply one WS depth update (absolute quantities)
-    pub fn apply_update(&mut self, ev: &DepthUpdate) {
-        // bids
+    /// Replace the whole book with one partial-depth (top-N) message.
+    pub fn replace_from_partial(&mut self, ev: &DepthUpdate) {
+        self.bids.clear();
+        self.asks.clear();
+
         for [p, q] in &ev.b {
-            let (p, q) = (Self::parse_f64(p), Self::parse_f64(q));
-            if q == 0.0 {
-                self.bids.remove(&OF(p));
-            } else {
-                self.bids.insert(OF(p), q);
+            let Some((p, q)) = Self::parse_level(p, q) else {
+                warn!(symbol=%self.symbol, price=%p, qty=%q, "Unparsable level skipped");
+                continue;
+            };
+            if !Self::is_zero(q) {
+                self.bids.insert(p, q);
             }
         }
-        // asks
         for [p, q] in &ev.a {
-            let (p, q) = (Self::parse_f64(p), Self::parse_f64(q));
-            if q == 0.0 {
-                self.asks.remove(&OF(p));
-            } else {
-                self.asks.insert(OF(p), q);
+            let Some((p, q)) = Self::parse_level(p, q) else {
+                warn!(symbol=%self.symbol, price=%p, qty=%q, "Unparsable level skipped");
+                continue;
+            };
+            if !Self::is_zero(q) {
+                self.asks.insert(p, q);
+            }
+        }
+        self.rebuild_ticks(&ev.b, &ev.a);
+        self.note_times(ev);
+        self.last_u = Some(ev.u);
+    }
+
+    /// Apply one WS depth update (absolute quantities)
+    pub fn apply_update(&mut self, ev: &DepthUpdate) {
+        self.apply_levels(ev, None);
+    }
+
+    /// Like [`apply_update`](Self::apply_update), also returning every level
+    /// whose quantity changed, bids first.
+    pub fn apply_update_with_changes(&mut self, ev: &DepthUpdate) -> Vec<LevelChange> {
+        let mut changes = Vec::with_capacity(ev.b.len() + ev.a.len());
+        self.apply_levels(ev, Some(&mut changes));
+        changes
+    }
+
+    fn apply_levels(&mut self, ev: &DepthUpdate, mut changes: Option<&mut Vec<LevelChange>>) {
+        for (side, levels) in [(BookSide::Bid, &ev.b), (BookSide::Ask, &ev.a)] {
+            let map = match side {
+                BookSide::Bid => &mut self.bids,
+                BookSide::Ask => &mut self.asks,
+            };
+            for [p, q] in levels {
+                let Some((p, q)) = Self::parse_level(p, q) else {
+                    warn!(symbol=%self.symbol, price=%p, qty=%q, "Unparsable level skipped");
+                    continue;
+                };
+                let old = if Self::is_zero(q) {
+                    map.remove(&p)
+                } else {
+                    map.insert(p, q)
+                };
+                if let Some(changes) = changes.as_deref_mut() {
+                    let old_qty = old.unwrap_or_default();
+                    if old_qty != q {
+                        changes.push(LevelChange {
+                            side,
+                            price: p,
+                            old_qty,
+                            new_qty: q,
+                        });
+                    }
+                }
             }
         }
+        if let (Some(filters), Some(ticks)) = (&self.filters, &mut self.ticks) {
+            Self::apply_ticks(filters, &mut ticks.bids, &ev.b);
+            Self::apply_ticks(filters, &mut ticks.asks, &ev.a);
+        }
+        self.note_times(ev);
         self.last_u = Some(ev.u);
     }
 
+    fn note_times(&mut self, ev: &DepthUpdate) {
+        // Spot partial-depth payloads carry no event time
+        if ev.E > 0 {
+            self.last_event_time = Some(ev.E);
+        }
+        self.last_transaction_time = ev.T;
+        self.last_received = ev.received;
+    }
+
     pub fn continuity_check<'a>(&mut self, du: &'a DepthUpdate) -> UpdateDecision<'a> {
         let snapshot_id = match self.snapshot_id {
             None => {
                 self.last_u = None;
                 return UpdateDecision::Resync(ResyncNeeded {
                     symbol: self.symbol.clone(),
+                    cause: ResyncCause::NoSnapshot,
                     expected_pu: None,
-                    got_pu: du.pu,
+                    got_pu: du.prev_u(),
                     got_U: du.U,
                     got_u: du.u,
                 });
@@ -181,52 +496,105 @@ impl OrderBook {
 
         match self.last_u {
             None => {
-                if du.u < snapshot_id {
+                // Futures: first event has U <= lastUpdateId <= u.
+                // Spot: first event has U <= lastUpdateId + 1 <= u.
+                let first_id = match du.pu {
+                    Some(_) => snapshot_id,
+                    None => snapshot_id + 1,
+                };
+
+                if du.u < first_id {
                     return UpdateDecision::Drop;
                 }
-            
-                if du.U <= snapshot_id && snapshot_id <= du.u {
+
+                if du.U <= first_id && first_id <= du.u {
                     self.last_u = Some(du.u);
                     return UpdateDecision::Apply(du);
                 }
-            
-                // du.U > snapshot_id => we missed the bridging update
+
+                // du.U > first_id => we missed the bridging update
                 debug!(
                     "Missed updates after initialization for {}, snap_id: {} U: {} u: {}",
                     du.s, snapshot_id, du.U, du.u,
                 );
                 self.last_u = None;
-                return UpdateDecision::Resync(ResyncNeeded {
+                UpdateDecision::Resync(ResyncNeeded {
                     symbol: self.symbol.clone(),
+                    cause: ResyncCause::MissedBridge,
                     expected_pu: None,
-                    got_pu: du.pu,
+                    got_pu: du.prev_u(),
                     got_U: du.U,
                     got_u: du.u,
-                });
+                })
             }
 
             Some(pu) => {
-                if pu == du.pu {
+                let prev_u = du.prev_u();
+                let (apply, drop) = match du.pu {
+                    Some(_) => (pu == prev_u, pu > prev_u),
+                    // Spot has no `pu`; an event overlapping `last_u` (from
+                    // the other connection at a handover) still carries new
+                    // levels in its tail, and quantities are absolute, so
+                    // applying it whole is safe.
+                    None => (du.U <= pu + 1 && pu < du.u, du.u <= pu),
+                };
+                if apply {
                     self.last_u = Some(du.u);
-                    return UpdateDecision::Apply(&du);
-                } else if pu > du.pu {
-                    return UpdateDecision::Drop;
+                    UpdateDecision::Apply(du)
+                } else if drop {
+                    UpdateDecision::Drop
                 } else {
                     self.last_u = None;
-                    return UpdateDecision::Resync(ResyncNeeded {
+                    UpdateDecision::Resync(ResyncNeeded {
                         symbol: self.symbol.clone(),
+                        cause: ResyncCause::SequenceGap,
                         expected_pu: Some(pu),
-                        got_pu: du.pu,
+                        got_pu: du.prev_u(),
                         got_U: du.U,
                         got_u: du.u,
-                    });
+                    })
                 }
             }
         }
     }
 
-    fn parse_f64(s: &str) -> f64 {
-        // Binance sends clean numeric strings
-        s.parse::<f64>().unwrap()
+    /// Parse one `[price, qty]` pair as sent by Binance, or `None` if either
+    /// isn't a number.
+    pub(crate) fn parse_level(p: &str, q: &str) -> Option<(Price, Qty)> {
+        #[cfg(not(feature = "decimal"))]
+        let level = (OF(p.parse::<f64>().ok()?), q.parse::<f64>().ok()?);
+        #[cfg(feature = "decimal")]
+        let level = (p.parse::<Price>().ok()?, q.parse::<Qty>().ok()?);
+        Some(level)
+    }
+
+    fn rebuild_ticks(&mut self, bids: &[[String; 2]], asks: &[[String; 2]]) {
+        let (Some(filters), Some(ticks)) = (&self.filters, &mut self.ticks) else {
+            return;
+        };
+        ticks.bids.clear();
+        ticks.asks.clear();
+        Self::apply_ticks(filters, &mut ticks.bids, bids);
+        Self::apply_ticks(filters, &mut ticks.asks, asks);
+    }
+
+    /// Applies levels to one side of the integer ladder, skipping off-grid ones.
+    fn apply_ticks(filters: &SymbolFilters, side: &mut OrdMap<i64, u64>, levels: &[[String; 2]]) {
+        for [p, q] in levels {
+            let (Some(ticks), Some(lots)) = (filters.price_to_ticks(p), filters.qty_to_lots(q))
+            else {
+                warn!(symbol=%filters.symbol, price=%p, qty=%q, "Off-grid level left out of tick ladder");
+                continue;
+            };
+            if lots == 0 {
+                side.remove(&ticks);
+            } else {
+                side.insert(ticks, lots);
+            }
+        }
+    }
+
+    fn is_zero(q: Qty) -> bool {
+        q == Qty::default()
     }
 }
@@ -0,0 +1,206 @@
+use crate::ob_manager::order_book::{price_to_f64, qty_to_f64, OrderBook, Price, Qty};
+
+/// Aggressor side of a simulated market order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Lifts asks, lowest first.
+    Buy,
+    /// Hits bids, highest first.
+    Sell,
+}
+
+/// Outcome of walking the book with a market order, see
+/// [`OrderBook::simulate_market_order`] and [`OrderBook::simulate_notional`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillEstimate {
+    pub side: Side,
+    /// Base quantity that would be filled.
+    pub filled_qty: f64,
+    /// Quote amount that would be spent (buy) or received (sell).
+    pub notional: f64,
+    /// `notional / filled_qty`; `None` if nothing fills.
+    pub avg_price: Option<f64>,
+    /// Price of the last level touched; `None` if nothing fills.
+    pub worst_price: Option<Price>,
+    /// Cost of `avg_price` against the touch, in basis points (`>= 0`).
+    pub slippage_bps: Option<f64>,
+    /// Levels touched, including a partially consumed last one.
+    pub levels_consumed: usize,
+    /// Size the book was too thin for, in the unit that was requested:
+    /// base quantity for `simulate_market_order`, quote for `simulate_notional`.
+    pub remaining: f64,
+}
+
+impl FillEstimate {
+    /// Whether the whole requested size fills against the current book.
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0.0
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Budget {
+    Base,
+    Quote,
+}
+
+/// Market order simulation against the current levels.
+///
+/// Nothing is allocated; a call walks only as many levels as the order needs.
+impl OrderBook {
+    /// Fills `qty` (base units) by walking asks for a buy or bids for a sell.
+    ///
+    /// Non-positive or NaN sizes fill nothing.
+    ///
+    /// ```
+    /// use binance_stream_handler::{OrderBook, Side};
+    ///
+    /// let mut book = OrderBook::new("ADAUSDT");
+    /// book.asks.insert("1.00".parse().unwrap(), "2".parse().unwrap());
+    /// book.asks.insert("1.10".parse().unwrap(), "2".parse().unwrap());
+    ///
+    /// let fill = book.simulate_market_order(Side::Buy, 3.0);
+    /// assert_eq!(fill.levels_consumed, 2);
+    /// assert!((fill.avg_price.unwrap() - 3.1 / 3.0).abs() < 1e-12);
+    /// assert!(fill.is_complete());
+    ///
+    /// let thin = book.simulate_market_order(Side::Buy, 5.0);
+    /// assert_eq!(thin.remaining, 1.0);
+    /// ```
+    pub fn simulate_market_order(&self, side: Side, qty: f64) -> FillEstimate {
+        self.simulate(side, qty, Budget::Base)
+    }
+
+    /// Spends (buy) or raises (sell) `quote_amount`, taking a fraction of the
+    /// last level as needed.
+    pub fn simulate_notional(&self, side: Side, quote_amount: f64) -> FillEstimate {
+        self.simulate(side, quote_amount, Budget::Quote)
+    }
+
+    fn simulate(&self, side: Side, target: f64, budget: Budget) -> FillEstimate {
+        match side {
+            Side::Buy => walk(side, self.asks_asc(), target, budget),
+            Side::Sell => walk(side, self.bids_desc(), target, budget),
+        }
+    }
+}
+
+fn walk(
+    side: Side,
+    levels: impl Iterator<Item = (Price, Qty)>,
+    target: f64,
+    budget: Budget,
+) -> FillEstimate {
+    let mut remaining = if target > 0.0 { target } else { 0.0 };
+    let mut filled_qty = 0.0;
+    let mut notional = 0.0;
+    let mut levels_consumed = 0;
+    let mut touch = None;
+    let mut worst_price = None;
+
+    for (p, q) in levels {
+        if remaining == 0.0 {
+            break;
+        }
+        let px = price_to_f64(p);
+        let qty = qty_to_f64(q);
+        let wanted = match budget {
+            Budget::Base => remaining,
+            Budget::Quote => remaining / px,
+        };
+
+        let take = if wanted <= qty {
+            // Partial (or exact) level: the order is done.
+            remaining = 0.0;
+            wanted
+        } else {
+            remaining -= match budget {
+                Budget::Base => qty,
+                Budget::Quote => qty * px,
+            };
+            qty
+        };
+
+        filled_qty += take;
+        notional += take * px;
+        levels_consumed += 1;
+        touch.get_or_insert(px);
+        worst_price = Some(p);
+    }
+
+    let avg_price = (filled_qty > 0.0).then(|| notional / filled_qty);
+    let slippage_bps = avg_price.zip(touch).map(|(avg, touch)| {
+        let diff = match side {
+            Side::Buy => avg - touch,
+            Side::Sell => touch - avg,
+        };
+        diff / touch * 10_000.0
+    });
+
+    FillEstimate {
+        side,
+        filled_qty,
+        notional,
+        avg_price,
+        worst_price,
+        slippage_bps,
+        levels_consumed,
+        remaining,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book() -> OrderBook {
+        let mut book = OrderBook::new("BTCUSDT");
+        for (p, q) in [("99", "2"), ("100", "1")] {
+            book.bids.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        for (p, q) in [("101", "1"), ("102", "2")] {
+            book.asks.insert(p.parse().unwrap(), q.parse().unwrap());
+        }
+        book
+    }
+
+    #[test]
+    fn order_past_the_book_depth_fills_what_is_there() {
+        let fill = book().simulate_market_order(Side::Buy, 5.0);
+        assert!(!fill.is_complete());
+        assert_eq!(fill.remaining, 2.0);
+        assert_eq!(fill.filled_qty, 3.0);
+        assert_eq!(fill.notional, 305.0);
+        assert_eq!(fill.levels_consumed, 2);
+        assert_eq!(fill.worst_price, Some("102".parse().unwrap()));
+    }
+
+    #[test]
+    fn sell_walks_bids_from_the_top() {
+        let fill = book().simulate_market_order(Side::Sell, 2.0);
+        assert!(fill.is_complete());
+        assert_eq!(fill.avg_price, Some(99.5));
+        assert!((fill.slippage_bps.unwrap() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn notional_takes_a_fraction_of_the_last_level() {
+        let fill = book().simulate_notional(Side::Buy, 152.0);
+        assert!(fill.is_complete());
+        assert_eq!(fill.filled_qty, 1.5);
+        assert_eq!(fill.levels_consumed, 2);
+
+        let thin = book().simulate_notional(Side::Buy, 400.0);
+        assert_eq!(thin.remaining, 95.0);
+    }
+
+    #[test]
+    fn non_positive_sizes_fill_nothing() {
+        for qty in [0.0, -1.0, f64::NAN] {
+            let fill = book().simulate_market_order(Side::Buy, qty);
+            assert_eq!(fill.filled_qty, 0.0);
+            assert_eq!(fill.avg_price, None);
+            assert_eq!(fill.levels_consumed, 0);
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::config::{Market, StreamConfig};
+use crate::error::Error;
+use crate::ob_manager::exchange_info::{request_symbol_filters, SymbolFilters};
+use crate::ob_manager::order_book::DepthSnapshot;
+use crate::telemetry;
+
+/// How long every request stands down after a 418/429 without `Retry-After`.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often an `Initial` request rechecks while `Resync`s are queued.
+const YIELD_RECHECK: Duration = Duration::from_millis(250);
+
+/// Which snapshot goes first when the weight budget runs short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SnapshotPriority {
+    /// Resync of a book that was live; its consumers are looking at stale data.
+    Resync,
+    /// First snapshot of a symbol, at startup or after `add_symbol`.
+    Initial,
+}
+
+/// REST client for depth snapshots, shared by every book of a pipeline.
+///
+/// Each request first takes its weight ([`Market::depth_weight`]) from a
+/// token bucket refilling at [`StreamConfig::rest_weight_budget`] per minute.
+/// The bucket is also trimmed to the budget minus `X-MBX-USED-WEIGHT-1M`, so
+/// weight used elsewhere on the same IP counts too. After a 418 or 429, all
+/// requests wait out `Retry-After`. `Resync` requests that are waiting hold
+/// back `Initial` ones.
+///
+/// A resync storm across hundreds of symbols therefore queues up instead of
+/// getting the IP banned. `exchangeInfo` requests for the tick ladder go
+/// through the same client and budget.
+#[derive(Debug)]
+pub struct SnapshotClient {
+    http: Client,
+    market: Market,
+    rest_base_url: String,
+    budget: f64,
+    bucket: Mutex<Bucket>,
+    resyncs_waiting: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// Counts a waiting `Resync` request for as long as it is alive.
+struct ResyncWaiting<'a>(&'a AtomicUsize);
+
+impl<'a> ResyncWaiting<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for ResyncWaiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl SnapshotClient {
+    pub fn new(config: &StreamConfig) -> Result<Self, Error> {
+        let http = Client::builder()
+            .user_agent("binance-stream-handler/0.1")
+            .build()
+            .map_err(|source| Error::Transport {
+                symbol: None,
+                source,
+            })?;
+        let budget = f64::from(config.rest_weight_budget());
+        Ok(Self {
+            http,
+            market: config.market(),
+            rest_base_url: config.rest_base_url().to_string(),
+            budget,
+            bucket: Mutex::new(Bucket {
+                tokens: budget,
+                refilled: Instant::now(),
+                blocked_until: None,
+            }),
+            resyncs_waiting: AtomicUsize::new(0),
+        })
+    }
+
+    /// Fetches a depth snapshot of `symbol` once the budget allows it.
+    pub async fn depth_snapshot(
+        &self,
+        symbol: &str,
+        limit: u16,
+        priority: SnapshotPriority,
+    ) -> Result<DepthSnapshot, Error> {
+        let sym = symbol.to_ascii_uppercase();
+        self.acquire(self.market.depth_weight(limit), priority).await;
+
+        let started = Instant::now();
+        let result = self.fetch_depth_snapshot(&sym, limit).await;
+        match &result {
+            Ok(_) => telemetry::snapshot_fetched(&sym, started.elapsed()),
+            Err(e) => telemetry::snapshot_failed(&sym, e),
+        }
+        result
+    }
+
+    /// Fetches the filters of `symbols` from `exchangeInfo` once the budget
+    /// allows it, see [`fetch_symbol_filters`].
+    ///
+    /// [`fetch_symbol_filters`]: crate::fetch_symbol_filters
+    pub async fn symbol_filters(
+        &self,
+        symbols: &[String],
+        priority: SnapshotPriority,
+    ) -> Result<HashMap<String, SymbolFilters>, Error> {
+        self.acquire(self.market.exchange_info_weight(), priority).await;
+        request_symbol_filters(&self.http, &self.rest_base_url, self.market, symbols, |resp| {
+            self.observe(&symbols.join(","), resp, retry_after(resp))
+        })
+        .await
+    }
+
+    /// Waits until `weight` can be taken from the bucket, then takes it.
+    async fn acquire(&self, weight: u32, priority: SnapshotPriority) {
+        // A request heavier than the whole budget would never fit; let it
+        // through on a full bucket instead.
+        let weight = f64::from(weight).min(self.budget);
+        let mut waiting = None;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.budget / 60.0).min(self.budget);
+                bucket.refilled = now;
+
+                match bucket.blocked_until.filter(|&until| until > now) {
+                    Some(until) => until - now,
+                    None if priority == SnapshotPriority::Initial
+                        && self.resyncs_waiting.load(Ordering::Relaxed) > 0 =>
+                    {
+                        YIELD_RECHECK
+                    }
+                    None if bucket.tokens >= weight => {
+                        bucket.tokens -= weight;
+                        return;
+                    }
+                    None => Duration::from_secs_f64((weight - bucket.tokens) * 60.0 / self.budget),
+                }
+            };
+            if priority == SnapshotPriority::Resync && waiting.is_none() {
+                waiting = Some(ResyncWaiting::new(&self.resyncs_waiting));
+            }
+            debug!(?priority, ?wait, "Snapshot request waiting for weight budget");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Syncs the bucket with the weight headers and status of a response.
+    fn observe(&self, symbol: &str, resp: &reqwest::Response, retry_after: Option<Duration>) {
+        let used = resp
+            .headers()
+            .get("x-mbx-used-weight-1m")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok());
+        self.note_response(symbol, resp.status().as_u16(), used, retry_after);
+    }
+
+    /// [`observe`](Self::observe) on the parsed status and headers.
+    fn note_response(&self, symbol: &str, status: u16, used: Option<u32>, retry_after: Option<Duration>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        if let Some(used) = used {
+            telemetry::rest_used_weight(used);
+            bucket.tokens = bucket.tokens.min(self.budget - f64::from(used));
+        }
+        if matches!(status, 418 | 429) {
+            let backoff = retry_after.unwrap_or(DEFAULT_BACKOFF);
+            warn!(symbol=%symbol, status, ?backoff, "REST rate limit hit; pausing all snapshot requests");
+            bucket.tokens = bucket.tokens.min(0.0);
+            bucket.blocked_until = Some(Instant::now() + backoff);
+        }
+    }
+
+    async fn fetch_depth_snapshot(&self, sym: &str, limit: u16) -> Result<DepthSnapshot, Error> {
+        let url = format!(
+            "{}{}?symbol={sym}&limit={limit}",
+            self.rest_base_url,
+            self.market.depth_path()
+        );
+
+        let transport = |source| Error::Transport {
+            symbol: Some(sym.to_string()),
+            source,
+        };
+
+        let resp = self.http.get(&url).send().await.map_err(transport)?;
+        let status = resp.status();
+        let retry_after = retry_after(&resp);
+        self.observe(sym, &resp, retry_after);
+        let body = resp.bytes().await.map_err(transport)?;
+
+        if !status.is_success() {
+            return Err(Error::from_response(Some(sym), status, retry_after, &body));
+        }
+
+        serde_json::from_slice(&body).map_err(|source| Error::Decode {
+            symbol: Some(sym.to_string()),
+            source,
+        })
+    }
+}
+
+/// `Retry-After` of a response, in seconds.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Client with a budget of 60 weight a minute, i.e. one a second.
+    fn client() -> SnapshotClient {
+        let config = StreamConfig::builder(["BTCUSDT"])
+            .rest_weight_budget(60)
+            .build()
+            .unwrap();
+        SnapshotClient::new(&config).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bucket_refills_at_the_budget_rate() {
+        let client = client();
+        let started = Instant::now();
+        client.acquire(60, SnapshotPriority::Initial).await;
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        client.acquire(30, SnapshotPriority::Initial).await;
+        assert_eq!(started.elapsed(), Duration::from_secs(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn request_heavier_than_the_budget_waits_for_a_full_bucket() {
+        let client = client();
+        client.acquire(10, SnapshotPriority::Initial).await;
+        let started = Instant::now();
+        client.acquire(250, SnapshotPriority::Initial).await;
+        assert_eq!(started.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn used_weight_header_trims_the_bucket() {
+        let client = client();
+        client.note_response("BTCUSDT", 200, Some(50), None);
+        let started = Instant::now();
+        client.acquire(20, SnapshotPriority::Initial).await;
+        assert_eq!(started.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_blocks_for_retry_after() {
+        let client = client();
+        client.note_response("BTCUSDT", 429, None, Some(Duration::from_secs(5)));
+        let started = Instant::now();
+        client.acquire(1, SnapshotPriority::Resync).await;
+        // Blocked for 5s, and the bucket was emptied by the 429.
+        assert!(started.elapsed() >= Duration::from_secs(5), "{:?}", started.elapsed());
+        assert!(started.elapsed() <= Duration::from_secs(6), "{:?}", started.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ban_without_retry_after_blocks_for_the_default_backoff() {
+        let client = client();
+        client.note_response("BTCUSDT", 418, None, None);
+        let started = Instant::now();
+        client.acquire(1, SnapshotPriority::Resync).await;
+        assert!(started.elapsed() >= DEFAULT_BACKOFF, "{:?}", started.elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_resync_goes_before_an_earlier_initial() {
+        let client = Arc::new(client());
+        client.acquire(60, SnapshotPriority::Initial).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let spawn = |priority| {
+            let (client, order) = (client.clone(), order.clone());
+            tokio::spawn(async move {
+                client.acquire(30, priority).await;
+                order.lock().unwrap().push(priority);
+            })
+        };
+        let initial = spawn(SnapshotPriority::Initial);
+        tokio::task::yield_now().await;
+        let resync = spawn(SnapshotPriority::Resync);
+        initial.await.unwrap();
+        resync.await.unwrap();
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            [SnapshotPriority::Resync, SnapshotPriority::Initial]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::StreamConfig;
+use crate::error::Error;
+use crate::ob_manager::exchange_info::{fetch_symbol_filters, SymbolFilters};
+use crate::ob_manager::order_book::DepthSnapshot;
+use crate::ob_manager::snapshot_client::{SnapshotClient, SnapshotPriority};
+
+/// Where book tasks get their depth snapshots from.
+///
+/// [`SnapshotClient`] (Binance REST) is what [`generate_orderbooks`] uses.
+/// [`FileSnapshots`] serves recorded responses and [`SnapshotFn`] wraps a
+/// closure, so the resync logic can run against deterministic snapshots or a
+/// local stand-in; pass them to [`generate_orderbooks_with`].
+///
+/// [`generate_orderbooks`]: crate::generate_orderbooks
+/// [`generate_orderbooks_with`]: crate::generate_orderbooks_with
+pub trait SnapshotProvider: Send + Sync {
+    /// Snapshot of `symbol` (upper case) with at most `limit` levels a side.
+    ///
+    /// `priority` is a scheduling hint; providers without a budget ignore it.
+    fn snapshot<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u16,
+        priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<DepthSnapshot, Error>>;
+
+    /// Tick/lot filters of `symbols` for [`StreamConfig::tick_ladder`].
+    ///
+    /// Defaults to [`fetch_symbol_filters`] against
+    /// [`StreamConfig::rest_base_url`].
+    fn symbol_filters<'a>(
+        &'a self,
+        config: &'a StreamConfig,
+        symbols: &'a [String],
+        _priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<HashMap<String, SymbolFilters>, Error>> {
+        Box::pin(fetch_symbol_filters(config, symbols))
+    }
+}
+
+impl SnapshotProvider for SnapshotClient {
+    fn snapshot<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u16,
+        priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<DepthSnapshot, Error>> {
+        Box::pin(self.depth_snapshot(symbol, limit, priority))
+    }
+
+    fn symbol_filters<'a>(
+        &'a self,
+        _config: &'a StreamConfig,
+        symbols: &'a [String],
+        priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<HashMap<String, SymbolFilters>, Error>> {
+        Box::pin(SnapshotClient::symbol_filters(self, symbols, priority))
+    }
+}
+
+/// Recorded snapshots, read from `<dir>/<SYMBOL>.json`.
+///
+/// Each file holds a depth endpoint response body. It is read again on every
+/// call, so a test can swap it between resyncs, and trimmed to `limit`
+/// levels a side.
+#[derive(Debug, Clone)]
+pub struct FileSnapshots {
+    dir: PathBuf,
+}
+
+impl FileSnapshots {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl SnapshotProvider for FileSnapshots {
+    fn snapshot<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u16,
+        _priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<DepthSnapshot, Error>> {
+        Box::pin(async move {
+            let path = self.dir.join(format!("{symbol}.json"));
+            let body = tokio::fs::read(&path).await.map_err(|source| Error::Io {
+                symbol: Some(symbol.to_string()),
+                source,
+            })?;
+            let mut snapshot: DepthSnapshot =
+                serde_json::from_slice(&body).map_err(|source| Error::Decode {
+                    symbol: Some(symbol.to_string()),
+                    source,
+                })?;
+            snapshot.truncate(limit.into());
+            Ok(snapshot)
+        })
+    }
+}
+
+/// A closure as a [`SnapshotProvider`], e.g. a mock returning canned books.
+///
+/// ```
+/// use binance_stream_handler::{
+///     DepthSnapshot, Error, SnapshotFn, SnapshotPriority, SnapshotProvider,
+/// };
+///
+/// # #[tokio::main(flavor = "current_thread")] async fn main() {
+/// let mock = SnapshotFn(|_symbol: &str, _limit: u16| -> Result<_, Error> {
+///     Ok(DepthSnapshot::new(
+///         100,
+///         vec![["99.5".into(), "2".into()]],
+///         vec![["100.5".into(), "1".into()]],
+///     ))
+/// });
+///
+/// let snap = mock.snapshot("BTCUSDT", 1000, SnapshotPriority::Initial).await.unwrap();
+/// assert_eq!(snap.last_update_id(), 100);
+/// # }
+/// ```
+pub struct SnapshotFn<F>(pub F);
+
+impl<F> fmt::Debug for SnapshotFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SnapshotFn")
+    }
+}
+
+impl<F> SnapshotProvider for SnapshotFn<F>
+where
+    F: Fn(&str, u16) -> Result<DepthSnapshot, Error> + Send + Sync,
+{
+    fn snapshot<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u16,
+        _priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<DepthSnapshot, Error>> {
+        Box::pin(std::future::ready((self.0)(symbol, limit)))
+    }
+}
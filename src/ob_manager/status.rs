@@ -0,0 +1,106 @@
+use std::time::SystemTime;
+use tokio::sync::watch;
+
+/// Lifecycle of one symbol's book.
+///
+/// ```text
+/// Initializing ──first applied update──▶ Live ◀──▶ Stale (no update for `stale_after`)
+///                                         │
+/// gap / off-grid / integrity / dropped WS ──▼
+///                                     Resyncing ──first applied update──▶ Live
+///
+/// failed snapshot ──▶ Degraded { attempts, reason } ──retry succeeds, first applied update──▶ Live
+///                         │
+///                         └─ retries exhausted / non-retryable error ──▶ Failed { reason }   (terminal)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeedStatus {
+    /// Waiting for the first snapshot, or for the first update bridging it.
+    Initializing,
+    /// The book is in sync and updating.
+    Live,
+    /// A sequence gap, a failed integrity check or a lost router connection
+    /// was detected; the published book is out of date until a fresh
+    /// snapshot is bridged.
+    Resyncing,
+    /// In sync, but no update arrived for [`StreamConfig::stale_after`].
+    /// Also the final state after the symbol is removed or the pipeline stops.
+    ///
+    /// [`StreamConfig::stale_after`]: crate::StreamConfig::stale_after
+    Stale,
+    /// Snapshot fetches are failing and being retried per
+    /// [`StreamConfig::snapshot_retry`]; the book is out of date meanwhile.
+    ///
+    /// [`StreamConfig::snapshot_retry`]: crate::StreamConfig::snapshot_retry
+    Degraded { attempts: u32, reason: String },
+    /// The book task stopped and the book will not change any more.
+    Failed { reason: String },
+}
+
+/// Health of one symbol's book, published next to it, see
+/// [`StreamHandle::feed_status`].
+///
+/// The receiver is only notified on status transitions; the timestamps are
+/// refreshed silently on every applied update and read with `borrow()`.
+///
+/// [`StreamHandle::feed_status`]: crate::StreamHandle::feed_status
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedHealth {
+    pub status: FeedStatus,
+    /// Local wall time the last update was applied.
+    pub last_update: Option<SystemTime>,
+    /// `E` of the last applied update (exchange event time, ms).
+    pub last_event_time: Option<u64>,
+}
+
+impl FeedHealth {
+    pub(crate) fn new() -> Self {
+        Self {
+            status: FeedStatus::Initializing,
+            last_update: None,
+            last_event_time: None,
+        }
+    }
+
+    /// Whether the book can be trusted right now.
+    pub fn is_live(&self) -> bool {
+        self.status == FeedStatus::Live
+    }
+}
+
+/// Moves the published health to `status`, notifying receivers if it changed.
+pub(crate) fn set_status(tx: &watch::Sender<FeedHealth>, status: FeedStatus) {
+    tx.send_if_modified(|h| {
+        if h.status == status {
+            return false;
+        }
+        h.status = status;
+        true
+    });
+}
+
+/// Records an applied update; notifies only if this brings the feed back to `Live`.
+pub(crate) fn mark_applied(tx: &watch::Sender<FeedHealth>, event_time: Option<u64>) {
+    tx.send_if_modified(|h| {
+        h.last_update = Some(SystemTime::now());
+        if event_time.is_some() {
+            h.last_event_time = event_time;
+        }
+        if h.status == FeedStatus::Live {
+            return false;
+        }
+        h.status = FeedStatus::Live;
+        true
+    });
+}
+
+/// `Live` → `Stale`; other states are left alone.
+pub(crate) fn mark_stale(tx: &watch::Sender<FeedHealth>) {
+    tx.send_if_modified(|h| {
+        if h.status != FeedStatus::Live {
+            return false;
+        }
+        h.status = FeedStatus::Stale;
+        true
+    });
+}
@@ -0,0 +1,233 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::ConfigError;
+use crate::error::Error;
+
+type EscalationHook = Arc<dyn Fn(&SnapshotEscalation<'_>) + Send + Sync>;
+
+/// A run of failed snapshot fetches, passed to [`RetryPolicy::on_escalate`].
+#[derive(Debug)]
+pub struct SnapshotEscalation<'a> {
+    pub symbol: &'a str,
+    /// Consecutive failed attempts so far.
+    pub attempts: u32,
+    /// The most recent failure.
+    pub error: &'a Error,
+    /// The book task stopped retrying; its status is now `Failed`.
+    pub gave_up: bool,
+}
+
+/// How book tasks retry failed REST snapshot fetches, and how the router
+/// reconnects a dropped WebSocket ([`StreamConfig::ws_reconnect`]).
+///
+/// The delay after the n-th failure is `initial_backoff * multiplier^(n-1)`,
+/// capped at `max_backoff` and scaled by a random factor in
+/// `[1 - jitter, 1]`. A `Retry-After` sent by the exchange is honoured as a
+/// lower bound. Snapshot errors that are not [`Error::is_retryable`]
+/// (unknown symbol, 4xx, undecodable body) end the task straight away.
+///
+/// [`StreamConfig::ws_reconnect`]: crate::StreamConfig::ws_reconnect
+///
+/// ```
+/// use std::time::Duration;
+/// use binance_stream_handler::{RetryPolicy, StreamConfig};
+///
+/// let policy = RetryPolicy::default()
+///     .initial_backoff(Duration::from_secs(1))
+///     .max_attempts(Some(20))
+///     .on_escalate(|e| {
+///         eprintln!("{}: {} failed snapshots, last: {}", e.symbol, e.attempts, e.error);
+///     });
+/// let cfg = StreamConfig::builder(["BTCUSDT"])
+///     .snapshot_retry(policy)
+///     .build()
+///     .unwrap();
+/// # let _ = cfg;
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) multiplier: f64,
+    pub(crate) jitter: f64,
+    pub(crate) max_attempts: Option<u32>,
+    pub(crate) escalate_after: u32,
+    on_escalate: Option<EscalationHook>,
+}
+
+impl Default for RetryPolicy {
+    /// 500ms doubling up to 30s, 50% jitter, retrying forever, escalating
+    /// after 5 failures in a row.
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_attempts: None,
+            escalate_after: 5,
+            on_escalate: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("max_attempts", &self.max_attempts)
+            .field("escalate_after", &self.escalate_after)
+            .field("on_escalate", &self.on_escalate.is_some())
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Delay after the first failure.
+    pub fn initial_backoff(mut self, delay: Duration) -> Self {
+        self.initial_backoff = delay;
+        self
+    }
+
+    /// Longest delay between two attempts.
+    pub fn max_backoff(mut self, delay: Duration) -> Self {
+        self.max_backoff = delay;
+        self
+    }
+
+    /// Growth factor between consecutive delays (`>= 1`).
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Fraction of each delay that is randomised away (`0..=1`), so books
+    /// that failed together don't retry in lockstep.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Attempts before the task gives up and reports `Failed`; `None`
+    /// retries transient errors forever.
+    pub fn max_attempts(mut self, attempts: Option<u32>) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Failures in a row after which [`on_escalate`](Self::on_escalate) is
+    /// called (once per run). It is also called when the task gives up.
+    pub fn escalate_after(mut self, attempts: u32) -> Self {
+        self.escalate_after = attempts;
+        self
+    }
+
+    /// Hook for alerting. Runs on the book task, so it should not block.
+    pub fn on_escalate<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&SnapshotEscalation<'_>) + Send + Sync + 'static,
+    {
+        self.on_escalate = Some(Arc::new(hook));
+        self
+    }
+
+    /// Checks the settings; `policy` names the builder field in the error.
+    pub(crate) fn validate(&self, policy: &'static str) -> Result<(), ConfigError> {
+        let reason = if self.initial_backoff.is_zero() {
+            "initial_backoff must be greater than zero"
+        } else if self.max_backoff < self.initial_backoff {
+            "max_backoff must not be below initial_backoff"
+        } else if self.multiplier.is_nan() || self.multiplier < 1.0 || self.multiplier.is_infinite() {
+            "multiplier must be a finite number >= 1"
+        } else if !(0.0..=1.0).contains(&self.jitter) {
+            "jitter must be within 0..=1"
+        } else if self.max_attempts == Some(0) {
+            "max_attempts must be greater than zero"
+        } else {
+            return Ok(());
+        };
+        Err(ConfigError::InvalidRetryPolicy { policy, reason })
+    }
+
+    /// Delay after `failures` consecutive failures (at least 1).
+    pub(crate) fn delay(&self, failures: u32, retry_after: Option<Duration>) -> Duration {
+        let exp = failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exp))
+            .min(self.max_backoff.as_secs_f64());
+        let delay = Duration::from_secs_f64(base * (1.0 - self.jitter * unit_random()));
+        retry_after.map_or(delay, |r| delay.max(r))
+    }
+
+    pub(crate) fn escalate(&self, escalation: &SnapshotEscalation<'_>) {
+        if let Some(hook) = &self.on_escalate {
+            hook(escalation);
+        }
+    }
+}
+
+/// Uniform-ish value in `[0, 1]` from std's randomly keyed hasher.
+fn unit_random() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .multiplier(2.0)
+            .jitter(0.0)
+    }
+
+    #[test]
+    fn delay_grows_by_the_multiplier_up_to_max_backoff() {
+        let p = policy();
+        let ms = |failures| p.delay(failures, None).as_millis();
+        assert_eq!(ms(1), 100);
+        assert_eq!(ms(2), 200);
+        assert_eq!(ms(4), 800);
+        assert_eq!(ms(5), 1000);
+        assert_eq!(ms(u32::MAX), 1000);
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_delay() {
+        let p = policy().jitter(0.5);
+        for _ in 0..100 {
+            let d = p.delay(2, None);
+            assert!(d >= Duration::from_millis(100) && d <= Duration::from_millis(200), "{d:?}");
+        }
+    }
+
+    #[test]
+    fn retry_after_is_a_lower_bound() {
+        let p = policy();
+        assert_eq!(p.delay(1, Some(Duration::from_secs(5))), Duration::from_secs(5));
+        assert_eq!(p.delay(3, Some(Duration::from_millis(10))), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn validate_rejects_unusable_policies() {
+        assert!(policy().validate("snapshot_retry").is_ok());
+        for bad in [
+            policy().initial_backoff(Duration::ZERO),
+            policy().max_backoff(Duration::from_millis(50)),
+            policy().multiplier(0.5),
+            policy().multiplier(f64::NAN),
+            policy().jitter(1.5),
+            policy().max_attempts(Some(0)),
+        ] {
+            assert!(bad.validate("snapshot_retry").is_err(), "{bad:?}");
+        }
+    }
+}
@@ -1,20 +1,93 @@
 use chrono::{NaiveTime, Timelike, Utc};
-use futures_util::{Stream, StreamExt};
-use std::collections::{HashMap, VecDeque};
-use std::pin::Pin;
-use std::time::{Duration as StdDur};
-use tokio::sync::{mpsc, watch};
-use tokio::time::sleep;
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::time::{Duration as StdDur, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, sleep_until, timeout};
+use tokio_tungstenite::tungstenite;
 use std::sync::Arc;
-use tokio::sync::Notify;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{error, info, trace, warn};
 
-mod streaming;
+pub(crate) mod source;
+pub(crate) mod streaming;
 
+use crate::config::{DepthStream, StreamConfig};
+use crate::error::Error;
+use crate::latency::{Connection, ConnectionLatency, ReceiveStamp};
 use crate::ob_manager::order_book::{CombinedDepthUpdate, DepthUpdate};
-use crate::router::streaming::TimedStream;
+use crate::retry::RetryPolicy;
+use crate::router::source::DepthSource;
+use crate::router::streaming::{symbol_from_stream, LiveStream, WsSource};
+use crate::telemetry::{self, RouterGauges};
 
-type DynDepth = Pin<Box<dyn Stream<Item = CombinedDepthUpdate> + Send>>;
+/// Connection events kept for a lagging [`StreamHandle::connection_events`]
+/// receiver.
+///
+/// [`StreamHandle::connection_events`]: crate::StreamHandle::connection_events
+const CONNECTION_EVENT_CAP: usize = 64;
+
+/// Longest a connection may take to open before the attempt counts as failed.
+const OPEN_TIMEOUT: StdDur = StdDur::from_secs(10);
+
+/// What the router sends down a symbol's channel to its book task.
+// Almost every message is an update; boxing them would cost an allocation each.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum RouterMessage {
+    /// A depth update of the symbol, in connection order.
+    Update(DepthUpdate),
+    /// The connection delivering the symbol dropped; updates resume from its
+    /// replacement, so the book has to be resynced.
+    ConnectionLost,
+}
+
+/// Lifecycle of a router connection, see
+/// [`StreamHandle::connection_events`].
+///
+/// [`StreamHandle::connection_events`]: crate::StreamHandle::connection_events
+#[derive(Debug, Clone)]
+pub enum ConnectionEvent {
+    /// The connection ended without being asked to: a Close frame, a network
+    /// error or a server disconnect. A reconnect is scheduled.
+    ///
+    /// `resync` lists the symbols marked for resync; it is empty when the
+    /// connection was the standby, or the other one took over.
+    Disconnected {
+        connection: Connection,
+        resync: Vec<String>,
+    },
+    /// A reconnect attempt failed. With `gave_up` no further attempt is made
+    /// until the next A/B mode change reopens the connection.
+    ReconnectFailed {
+        connection: Connection,
+        attempts: u32,
+        error: Arc<Error>,
+        gave_up: bool,
+    },
+    /// The connection is back, subscribed to every stream again.
+    Reconnected {
+        connection: Connection,
+        attempts: u32,
+        downtime: StdDur,
+    },
+}
+
+/// Requests sent to the running router task to change the symbol set.
+pub enum RouterCommand {
+    Subscribe {
+        symbol: String,
+        stream: DepthStream,
+        tx: mpsc::Sender<RouterMessage>,
+        ack: oneshot::Sender<()>,
+    },
+    Unsubscribe {
+        symbol: String,
+        ack: oneshot::Sender<()>,
+    },
+}
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Mode {
@@ -23,171 +96,354 @@ enum Mode {
     OnlyB,
 }
 
-#[derive(PartialEq, Debug)]
+/// The connection whose updates are forwarded; the other one's are parked.
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Active {
     A,
     B,
 }
 
+impl Active {
+    fn other(self) -> Self {
+        match self {
+            Active::A => Active::B,
+            Active::B => Active::A,
+        }
+    }
+}
+
+/// A dropped connection waiting for its next reconnect attempt.
+struct Reconnect {
+    /// Attempts made so far.
+    attempts: u32,
+    dropped: Instant,
+    next_at: tokio::time::Instant,
+}
+
+impl Reconnect {
+    fn new(policy: &RetryPolicy) -> Self {
+        Self {
+            attempts: 0,
+            dropped: Instant::now(),
+            next_at: tokio::time::Instant::now() + policy.delay(1, None),
+        }
+    }
+}
+
+/// An open of connection A or B in flight.
+///
+/// Polled as its own branch of the router loop, so the other connection
+/// keeps being read while this one dials.
+struct Dial {
+    /// Streams the connection is being opened with.
+    streams: Vec<(String, DepthStream)>,
+    open: BoxFuture<'static, Result<LiveStream, Error>>,
+}
+
+impl Dial {
+    fn new(conn: Connection, source: &Arc<dyn DepthSource>, streams: &[(String, DepthStream)]) -> Self {
+        let source = source.clone();
+        let dialed = streams.to_vec();
+        let open = Box::pin(async move {
+            match timeout(OPEN_TIMEOUT, source.open(conn, &dialed)).await {
+                Ok(res) => res,
+                Err(_) => Err(Error::ws_handshake(tungstenite::Error::Io(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no connection after {OPEN_TIMEOUT:?}"),
+                )))),
+            }
+        });
+        Self {
+            streams: streams.to_vec(),
+            open,
+        }
+    }
+}
+
+/// Owns the router and mode-clock tasks started by [`DualRouter::start_dual_router`].
+pub struct RouterHandle {
+    pub commands: mpsc::Sender<RouterCommand>,
+    pub latency: Arc<ConnectionLatency>,
+    /// Disconnects and reconnects; call `subscribe()` to listen.
+    pub events: broadcast::Sender<ConnectionEvent>,
+    shutdown: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl RouterHandle {
+    /// Stops both tasks, closes the open sockets and drops the per-symbol
+    /// senders so book tasks can drain what is already queued.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        for task in self.tasks {
+            if let Err(e) = task.await {
+                warn!(error=%e, "Router task failed");
+            }
+        }
+    }
+}
+
+/// Resolves once shutdown is requested. Never resolves if the sender was
+/// dropped without requesting it, so a discarded handle doesn't stop anything.
+pub(crate) async fn shutdown_requested(rx: &mut watch::Receiver<bool>) {
+    if rx.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Resolves once the first connection is open, or with the reason none
+/// could be.
+pub type Started = oneshot::Receiver<Result<(), Error>>;
+
 pub struct DualRouter {
-    pub switch_cutoff: (NaiveTime, NaiveTime),
-    pub currency_pairs: &'static [&'static str],
-    stream_a: TimedStream,
-    stream_b: TimedStream,
+    pub config: Arc<StreamConfig>,
+    source: Arc<dyn DepthSource>,
+    /// Upper-case symbols and the depth stream each is subscribed to.
+    streams: Vec<(String, DepthStream)>,
 }
 
 impl DualRouter {
-    pub fn new(
-        switch_cutoff: (NaiveTime, NaiveTime),
-        currency_pairs: &'static [&'static str],
-    ) -> Self {
-        let life_span_a = switch_cutoff;
-        let life_span_b = (switch_cutoff.1, switch_cutoff.0);
-
-        let stream_a = TimedStream {
-            currency_pairs: currency_pairs,
-            life_span: (life_span_a),
-        };
-        let stream_b = TimedStream {
-            currency_pairs: currency_pairs,
-            life_span: (life_span_b),
-        };
+    /// Router whose A and B connections are opened through `source`.
+    pub fn new(config: Arc<StreamConfig>, source: Arc<dyn DepthSource>) -> Self {
+        let streams: Vec<(String, DepthStream)> = config
+            .currency_pairs()
+            .iter()
+            .map(|sym| (sym.clone(), config.depth_stream_for(sym)))
+            .collect();
 
         Self {
-            switch_cutoff,
-            currency_pairs,
-            stream_a,
-            stream_b,
+            config,
+            source,
+            streams,
         }
     }
 
     pub fn start_dual_router(
         &self,
-        chan_cap: usize,
-        park_cap: usize,
-    ) -> (HashMap<String, mpsc::Receiver<DepthUpdate>>, Arc<Notify>) {
-        let mut out_map = HashMap::<String, mpsc::Sender<DepthUpdate>>::new();
-        let mut rx_map = HashMap::<String, mpsc::Receiver<DepthUpdate>>::new();
-
-        for &sym in self.currency_pairs {
-            let (tx, rx) = mpsc::channel::<DepthUpdate>(chan_cap);
-            out_map.insert(sym.to_string(), tx);
-            rx_map.insert(sym.to_string(), rx);
+    ) -> (
+        HashMap<String, mpsc::Receiver<RouterMessage>>,
+        Started,
+        RouterHandle,
+    ) {
+        let mut out_map = HashMap::<String, mpsc::Sender<RouterMessage>>::new();
+        let mut rx_map = HashMap::<String, mpsc::Receiver<RouterMessage>>::new();
+
+        for sym in self.config.currency_pairs() {
+            let (tx, rx) = mpsc::channel::<RouterMessage>(self.config.chan_cap());
+            out_map.insert(sym.clone(), tx);
+            rx_map.insert(sym.clone(), rx);
         }
 
+        let park_cap = self.config.park_cap();
         let mut park: HashMap<String, VecDeque<DepthUpdate>> = self
-            .currency_pairs
+            .config
+            .currency_pairs()
+            .iter()
+            .map(|s| (s.clone(), VecDeque::with_capacity(park_cap)))
+            .collect();
+        let mut gauges: HashMap<String, RouterGauges> = self
+            .config
+            .currency_pairs()
             .iter()
-            .map(|s| (s.to_string(), VecDeque::with_capacity(park_cap)))
+            .map(|s| (s.clone(), RouterGauges::new(s)))
             .collect();
 
         let (ctrl_tx, mut ctrl_rx) = watch::channel(Mode::OnlyA);
+        let (cmd_tx, mut cmd_rx) = mpsc::channel::<RouterCommand>(32);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut router_shutdown_rx = shutdown_rx.clone();
         
         // Guards the obs initialisation before ws start feeding data. 
-        // Notifies the generate_orderbooks() that ws is connected and obs can be initialized.  
-        let connected_notify = Arc::new(Notify::new());
-        let connected_notify_task = connected_notify.clone();
+        // Tells generate_orderbooks() that ws is connected and obs can be
+        // initialized, or why no connection could be opened.
+        let (started_tx, started_rx) = oneshot::channel();
+        let mut started = Some(started_tx);
+
+        let latency = Arc::new(ConnectionLatency::default());
+        let router_latency = latency.clone();
+
+        let (conn_events, _) = broadcast::channel(CONNECTION_EVENT_CAP);
+        let router_events = conn_events.clone();
+        let reconnect_policy = self.config.ws_reconnect().clone();
 
-        let switch_cutoff = self.switch_cutoff;
-        tokio::spawn(rout_mode(switch_cutoff, ctrl_tx));
+        let mode_task = tokio::spawn(rout_mode(
+            self.config.switch_cutoffs(),
+            self.config.overlap(),
+            ctrl_tx,
+            shutdown_rx,
+        ));
 
-        let currency_pairs = self.currency_pairs;
-        let life_span_a = self.stream_a.life_span;
-        let life_span_b = self.stream_b.life_span;
+        let source = self.source.clone();
+        let mut streams = self.streams.clone();
 
-        tokio::spawn(async move {
+        let router_task = tokio::spawn(async move {
             let mut active: Option<Active> = None;
 
             let mut prev_u_by_sym: HashMap<String, u64> = HashMap::new();
 
+            // Top-N streams aren't sequenced, so skip the health check for them
+            let mut partial_syms: HashSet<String> = streams
+                .iter()
+                .filter(|(_, stream)| stream.is_partial())
+                .map(|(sym, _)| sym.clone())
+                .collect();
+
             // Lazily-opened runtime streams
-            let mut stream_a: Option<DynDepth> = None;
-            let mut stream_b: Option<DynDepth> = None;
+            let mut stream_a: Option<LiveStream> = None;
+            let mut stream_b: Option<LiveStream> = None;
+
+            // Pending reconnects of dropped (or unopenable) connections
+            let mut reconnect_a: Option<Reconnect> = None;
+            let mut reconnect_b: Option<Reconnect> = None;
+
+            // Opens and reconnect attempts in flight
+            let mut dial_a: Option<Dial> = None;
+            let mut dial_b: Option<Dial> = None;
 
-            // capture the configured per-symbol bound
-            let park_cap_local = park.values().next().map(|v| v.capacity()).unwrap_or(0);
+            // Id for SUBSCRIBE/UNSUBSCRIBE requests
+            let mut next_request_id: u64 = 1;
+
+            let park_cap_local = park_cap;
 
             let mut pending_mode: Option<Mode> = None;
             let mut mode = *ctrl_rx.borrow();
 
-            let mut connected_signaled = false;
-
-            match mode {
-                Mode::OnlyA => {
-                    open_stream(&mut stream_a, currency_pairs, life_span_a).await;
-                    if !connected_signaled && stream_a.is_some() {
-                        connected_notify_task.notify_waiters();
-                        connected_signaled = true;
-                    }
-                    stream_b = None;
-                    active = Some(Active::A);
-                    flush_park(&mut out_map, &mut park).await;
-                }
-                Mode::OnlyB => {
-                    open_stream(&mut stream_b, currency_pairs, life_span_b).await;
-                    if !connected_signaled && stream_b.is_some() {
-                        connected_notify_task.notify_waiters();
-                        connected_signaled = true;
-                    }
-                    stream_a = None;
-                    active = Some(Active::B);
-                    flush_park(&mut out_map, &mut park).await;
-                }
+            let open_error = match mode {
+                Mode::OnlyA => open_stream(Connection::A, &mut stream_a, &source, &streams).await.err(),
+                Mode::OnlyB => open_stream(Connection::B, &mut stream_b, &source, &streams).await.err(),
                 Mode::BothAB => {
-                    open_stream(&mut stream_a, currency_pairs, life_span_a).await;
-                    open_stream(&mut stream_b, currency_pairs, life_span_b).await;
-                    if !connected_signaled && (stream_a.is_some() || stream_b.is_some()) {
-                        connected_notify_task.notify_waiters();
-                        connected_signaled = true;
-                    }
-                    active = Some(Active::A);
+                    let opened_a = open_stream(Connection::A, &mut stream_a, &source, &streams).await;
+                    let opened_b = open_stream(Connection::B, &mut stream_b, &source, &streams).await;
+                    opened_a.err().or(opened_b.err())
                 }
+            };
+            // Forward from whichever connection opened.
+            settle(mode, &mut active, &mut stream_a, &mut stream_b, &mut out_map, &mut park).await;
+            // Set once startup failed for good; the router then stops.
+            let mut startup_failed = false;
+            match open_error {
+                _ if stream_a.is_some() || stream_b.is_some() => report_started(&mut started, Ok(())),
+                // e.g. a 403/451 handshake rejection; retrying won't help.
+                Some(e) if !e.is_retryable() => {
+                    error!(error=%e, "Router: no WS connection could be opened");
+                    report_started(&mut started, Err(e));
+                    startup_failed = true;
+                }
+                _ => {}
             }
+            schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
 
             // 6) main loop: react to mode changes and stream events
-            loop {
+            while !startup_failed {
                 if let Some(new_mode) = pending_mode.take() {
                     mode = apply_transition(
                         mode,
                         new_mode,
                         &mut active,
-                        &mut stream_a,
-                        &mut stream_b,
-                        currency_pairs,
-                        life_span_a,
-                        life_span_b,
+                        [(&mut stream_a, &mut dial_a, &reconnect_a), (&mut stream_b, &mut dial_b, &reconnect_b)],
+                        &source,
+                        &streams,
                         &mut out_map,
                         &mut park,
                     )
                     .await;
+                    schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
                 }
 
                 let a_open = stream_a.is_some();
                 let b_open = stream_b.is_some();
+                let a_retry_at = reconnect_a.as_ref().map(|r| r.next_at).filter(|_| dial_a.is_none());
+                let b_retry_at = reconnect_b.as_ref().map(|r| r.next_at).filter(|_| dial_b.is_none());
 
                 tokio::select! {
+                    _ = shutdown_requested(&mut router_shutdown_rx) => {
+                        info!("Router: shutdown requested");
+                        break;
+                    }
                     // 6a) time-based mode change
                     changed = ctrl_rx.changed() => {
                         if changed.is_err() { break; }
                         pending_mode = Some(*ctrl_rx.borrow_and_update());
                     }
+                    // Runtime subscribe/unsubscribe requests
+                    Some(cmd) = cmd_rx.recv() => {
+                        match cmd {
+                            RouterCommand::Subscribe { symbol, stream, tx, ack } => {
+                                if !streams.iter().any(|(s, _)| s == &symbol) {
+                                    info!(symbol=%symbol, spec=%stream.spec(), "Router: subscribing");
+                                    let name = WsSource::stream_name(&symbol, stream);
+                                    send_control("SUBSCRIBE", name, next_request_id, open_controls(&stream_a, &stream_b)).await;
+                                    next_request_id += 1;
+                                    streams.push((symbol.clone(), stream));
+                                }
+                                if stream.is_partial() {
+                                    partial_syms.insert(symbol.clone());
+                                }
+                                park.insert(symbol.clone(), VecDeque::with_capacity(park_cap_local));
+                                gauges.insert(symbol.clone(), RouterGauges::new(&symbol));
+                                out_map.insert(symbol, tx);
+                                let _ = ack.send(());
+                            }
+                            RouterCommand::Unsubscribe { symbol, ack } => {
+                                info!(symbol=%symbol, "Router: unsubscribing");
+                                if let Some(pos) = streams.iter().position(|(s, _)| s == &symbol) {
+                                    let (_, stream) = streams.remove(pos);
+                                    let name = WsSource::stream_name(&symbol, stream);
+                                    send_control("UNSUBSCRIBE", name, next_request_id, open_controls(&stream_a, &stream_b)).await;
+                                    next_request_id += 1;
+                                }
+                                partial_syms.remove(&symbol);
+                                out_map.remove(&symbol);
+                                park.remove(&symbol);
+                                gauges.remove(&symbol);
+                                prev_u_by_sym.remove(&symbol);
+                                let _ = ack.send(());
+                            }
+                        }
+                    }
+                    // Redial a dropped connection once its backoff ran out
+                    _ = sleep_until(a_retry_at.unwrap_or_else(tokio::time::Instant::now)), if a_retry_at.is_some() => {
+                        dial_a = redial(Connection::A, &mut reconnect_a, &source, &streams);
+                    }
+                    _ = sleep_until(b_retry_at.unwrap_or_else(tokio::time::Instant::now)), if b_retry_at.is_some() => {
+                        dial_b = redial(Connection::B, &mut reconnect_b, &source, &streams);
+                    }
+                    opened = async {
+                        match &mut dial_a { Some(dial) => dial.open.as_mut().await, None => std::future::pending().await }
+                    } => {
+                        let dialed = dial_a.take().map(|dial| dial.streams).unwrap_or_default();
+                        let b_up = stream_b.is_some() || dial_b.is_some() || reconnect_b.is_some();
+                        startup_failed = opened_stream(Connection::A, mode, opened, &dialed, &mut stream_a, &mut reconnect_a, &streams, &mut next_request_id, &reconnect_policy, &router_events, &mut started, b_up).await;
+                        settle(mode, &mut active, &mut stream_a, &mut stream_b, &mut out_map, &mut park).await;
+                        schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
+                    }
+                    opened = async {
+                        match &mut dial_b { Some(dial) => dial.open.as_mut().await, None => std::future::pending().await }
+                    } => {
+                        let dialed = dial_b.take().map(|dial| dial.streams).unwrap_or_default();
+                        let a_up = stream_a.is_some() || dial_a.is_some() || reconnect_a.is_some();
+                        startup_failed = opened_stream(Connection::B, mode, opened, &dialed, &mut stream_b, &mut reconnect_b, &streams, &mut next_request_id, &reconnect_policy, &router_events, &mut started, a_up).await;
+                        settle(mode, &mut active, &mut stream_a, &mut stream_b, &mut out_map, &mut park).await;
+                        schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
+                    }
                     // 6b) Stream A events
                     maybe_env = async {
-                        if let Some(s) = &mut stream_a { s.next().await } else {None}
+                        if let Some(s) = &mut stream_a { s.events.next().await } else {None}
                     }, if a_open => {
                         match maybe_env {
                             Some(env) => {
-                                let CombinedDepthUpdate {data: du, ..} = env;
+                                let du = received(env);
                                 let sym = du.s.to_ascii_uppercase();
+                                router_latency.record(Connection::A, &du);
                                 
                                 // Internal continuity helth check
-                           
-                                if let Some(prev_u) = prev_u_by_sym.get(&sym).copied() {
-                                    if du.pu > prev_u {
+                                let prev_u = prev_u_by_sym.get(&sym).copied().filter(|_| !partial_syms.contains(&sym));
+                                if let Some(prev_u) = prev_u {
+                                    if du.prev_u() > prev_u {
                                         warn!(
                                             symbol=%sym,
                                             prev_u,
-                                            got_pu=du.pu,
+                                            got_pu=du.prev_u(),
                                             got_U=du.U,
                                             got_u=du.u,
                                             ?mode,
@@ -199,55 +455,51 @@ impl DualRouter {
 
                                 prev_u_by_sym.insert(sym.clone(), du.u);
 
-                                match mode {
-                                    Mode::OnlyA => {
-                                        if let Some(tx) = out_map.get(&sym) {
-                                                if let Err(e) = tx.send(du).await {
-                                                    warn!(symbol=%sym, error=%e, "Router: per-symbol channel closed; dropping update");
-                                                }
-                                        }
-                                    }
-                                    Mode::BothAB => {
-                                        if active == Some(Active::A) {
-                                            if let Some(tx) = out_map.get(&sym) {
-                                                    if let Err(e) = tx.send(du).await {
-                                                        warn!(symbol=%sym, error=%e, "Router: per-symbol channel closed; dropping update");
-                                                    }
-                                            }
-                                        } else {
-                                            if let Some(buf) = park.get_mut(&sym) {
-                                                if buf.len() >= park_cap_local { buf.pop_front(); }
-                                                buf.push_back(du);
-                                            }
-                                        }
-                                    }
-                                    Mode::OnlyB => {
-                                        // A is closed
-                                    }
+                                if active == Some(Active::A) {
+                                    forward(&out_map, &gauges, &sym, du).await;
+                                } else {
+                                    park_update(&mut park, &gauges, &sym, du, park_cap_local);
                                 }
                             }
                             None => {
-                                //stream_a = None;
+                                warn!("Router: connection A ended");
+                                stream_a = None;
+                                let resync = connection_lost(
+                                    Connection::A,
+                                    &mut active,
+                                    stream_b.is_some(),
+                                    &mut out_map,
+                                    &mut park,
+                                    &mut prev_u_by_sym,
+                                )
+                                .await;
+                                schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
+                                let _ = router_events.send(ConnectionEvent::Disconnected {
+                                    connection: Connection::A,
+                                    resync,
+                                });
                             }
                         }
                     }
 
                     // Stream B events
                     maybe_env = async {
-                        if let Some(s) = &mut stream_b { s.next().await } else { None }
+                        if let Some(s) = &mut stream_b { s.events.next().await } else { None }
                     }, if b_open => {
                         match maybe_env {
                             Some(env) => {
-                                let CombinedDepthUpdate { data: du, .. } = env;
+                                let du = received(env);
                                 let sym = du.s.to_ascii_uppercase();
+                                router_latency.record(Connection::B, &du);
                                 
-                                // Internal continuity helth check 
-                                if let Some(prev_u) = prev_u_by_sym.get(&sym).copied() {
-                                    if du.pu > prev_u {
+                                // Internal continuity helth check
+                                let prev_u = prev_u_by_sym.get(&sym).copied().filter(|_| !partial_syms.contains(&sym));
+                                if let Some(prev_u) = prev_u {
+                                    if du.prev_u() > prev_u {
                                         warn!(
                                             symbol=%sym,
                                             prev_u,
-                                            got_pu=du.pu,
+                                            got_pu=du.prev_u(),
                                             got_U=du.U,
                                             got_u=du.u,
                                             ?mode,
@@ -259,132 +511,475 @@ impl DualRouter {
                                 
                                 prev_u_by_sym.insert(sym.clone(), du.u);
                                 
-                                match mode {
-                                    Mode::OnlyB => {
-                                        if let Some(tx) = out_map.get(&sym) {
-                                                if let Err(e) = tx.send(du).await {
-                                                    warn!(symbol=%sym, error=%e, "Router: per-symbol channel closed; dropping update");
-                                                }
-                                        }
-                                    }
-                                    Mode::BothAB => {
-                                        if active == Some(Active::B) {
-                                            if let Some(tx) = out_map.get(&sym) {
-                                                if let Err(e) = tx.send(du).await {
-                                                    warn!(symbol=%sym, error=%e, "Router: per-symbol channel closed; dropping update");
-                                                }                                                
-                                            }
-                                        } else {
-                                            if let Some(buf) = park.get_mut(&sym) {
-                                                if buf.len() >= park_cap_local { buf.pop_front(); }
-                                                buf.push_back(du);
-                                            }
-                                        }
-                                    }
-                                    Mode::OnlyA => {
-                                        // B is closed
-                                    }
+                                if active == Some(Active::B) {
+                                    forward(&out_map, &gauges, &sym, du).await;
+                                } else {
+                                    park_update(&mut park, &gauges, &sym, du, park_cap_local);
                                 }
                             }
                             None => {
-                                //stream_b = None;
+                                warn!("Router: connection B ended");
+                                stream_b = None;
+                                let resync = connection_lost(
+                                    Connection::B,
+                                    &mut active,
+                                    stream_a.is_some(),
+                                    &mut out_map,
+                                    &mut park,
+                                    &mut prev_u_by_sym,
+                                )
+                                .await;
+                                schedule_reconnects(mode, [(&stream_a, &dial_a, &mut reconnect_a), (&stream_b, &dial_b, &mut reconnect_b)], &reconnect_policy);
+                                let _ = router_events.send(ConnectionEvent::Disconnected {
+                                    connection: Connection::B,
+                                    resync,
+                                });
                             }
                         }
                     }
                 }
             }
+
+            // Close sockets first so nothing new arrives, then drop the
+            // per-symbol senders: book tasks drain their queues and exit.
+            for live in [stream_a.take(), stream_b.take()].into_iter().flatten() {
+                live.close().await;
+            }
+            drop(out_map);
+            info!("Router: stopped");
         });
-        (rx_map, connected_notify)
+        let router = RouterHandle {
+            commands: cmd_tx,
+            latency,
+            events: conn_events,
+            shutdown: shutdown_tx,
+            tasks: vec![router_task, mode_task],
+        };
+        (rx_map, started_rx, router)
+    }
+}
+
+/// The event of `env`, stamped with its receive time unless the source did,
+/// and with the symbol of a spot partial (which has none) taken from the
+/// stream name.
+fn received(env: CombinedDepthUpdate) -> DepthUpdate {
+    let CombinedDepthUpdate { stream, data: mut du } = env;
+    du.received.get_or_insert_with(ReceiveStamp::now);
+    if du.s.is_empty() {
+        du.s = symbol_from_stream(&stream);
+    }
+    du
+}
+
+/// Sends `du` to its symbol's book task, if the symbol is still routed.
+async fn forward(
+    out_map: &HashMap<String, mpsc::Sender<RouterMessage>>,
+    gauges: &HashMap<String, RouterGauges>,
+    sym: &str,
+    du: DepthUpdate,
+) {
+    if let Some(tx) = out_map.get(sym) {
+        if let Err(e) = tx.send(RouterMessage::Update(du)).await {
+            warn!(symbol=%sym, error=%e, "Router: per-symbol channel closed; dropping update");
+        }
+        if let Some(g) = gauges.get(sym) {
+            g.channel_fill(tx);
+        }
+    }
+}
+
+/// Buffers `du` from the standby connection, evicting the oldest when full.
+fn park_update(
+    park: &mut HashMap<String, VecDeque<DepthUpdate>>,
+    gauges: &HashMap<String, RouterGauges>,
+    sym: &str,
+    du: DepthUpdate,
+    park_cap: usize,
+) {
+    if let Some(buf) = park.get_mut(sym) {
+        if buf.len() >= park_cap {
+            buf.pop_front();
+        }
+        buf.push_back(du);
+        if let Some(g) = gauges.get(sym) {
+            g.park_len(buf.len());
+        }
+    }
+}
+
+/// Control channels of the currently open connections.
+fn open_controls(
+    stream_a: &Option<LiveStream>,
+    stream_b: &Option<LiveStream>,
+) -> Vec<mpsc::Sender<String>> {
+    [stream_a, stream_b]
+        .into_iter()
+        .flatten()
+        .filter_map(|live| live.control.clone())
+        .collect()
+}
+
+/// Sends a SUBSCRIBE/UNSUBSCRIBE for `stream_name` on every given connection.
+async fn send_control(
+    method: &str,
+    stream_name: String,
+    id: u64,
+    controls: Vec<mpsc::Sender<String>>,
+) {
+    let request = WsSource::control_message(method, &[stream_name], id);
+    for control in controls {
+        if let Err(e) = control.send(request.clone()).await {
+            warn!(%request, error=%e, "Router: WS control channel closed");
+        }
     }
 }
 
+/// Moves the router from `old` to `new` mode.
+///
+/// Connections `new` wants are dialed in the background (unless a
+/// reconnect is already pending); [`settle`] hands over once the incoming
+/// one is open. `slots` are A's and B's stream, dial and pending reconnect.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 async fn apply_transition(
     old: Mode,
     new: Mode,
     active: &mut Option<Active>,
-    stream_a: &mut Option<DynDepth>,
-    stream_b: &mut Option<DynDepth>,
-    currency_pairs: &'static [&'static str],
-    life_span_a: (NaiveTime, NaiveTime),
-    life_span_b: (NaiveTime, NaiveTime),
-    out_map: &mut HashMap<String, mpsc::Sender<DepthUpdate>>,
+    slots: [(&mut Option<LiveStream>, &mut Option<Dial>, &Option<Reconnect>); 2],
+    source: &Arc<dyn DepthSource>,
+    streams: &[(String, DepthStream)],
+    out_map: &mut HashMap<String, mpsc::Sender<RouterMessage>>,
     park: &mut HashMap<String, VecDeque<DepthUpdate>>,
 ) -> Mode {
-    match (old, new) {
-        (o, n) if o == n => {
-            trace!("Pseudo mode flip occured");
-            return n;
-        }
-        // OnlyA → BothAB: keep A primary, open B (start parking B)
-        (Mode::OnlyA, Mode::BothAB) => {
-            open_stream(stream_b, currency_pairs, life_span_b).await;
-            *active = Some(Active::A);
-        }
-        // BothAB → OnlyB: flush parked (B), close A, A→None, B becomes primary
-        (Mode::BothAB, Mode::OnlyB) => {
-            flush_park(out_map, park).await;
-            *stream_a = None;
-            open_stream(stream_b, currency_pairs, life_span_b).await;
-            *active = Some(Active::B);
-        }
-        // OnlyB → BothAB: keep B primary, open A (start parking A)
-        (Mode::OnlyB, Mode::BothAB) => {
-            open_stream(stream_a, currency_pairs, life_span_a).await;
-            *active = Some(Active::B);
-        }
-        // BothAB → OnlyA: flush parked (A), close B, B→None, A becomes primary
-        (Mode::BothAB, Mode::OnlyA) => {
-            flush_park(out_map, park).await;
-            *stream_b = None;
-            open_stream(stream_a, currency_pairs, life_span_a).await;
-            *active = Some(Active::A);
-        }
-        _ => {
-            panic!("Unexpected channel/mode switch from {:?} to {:?}", old, new);
+    if old == new {
+        trace!("Pseudo mode flip occured");
+        return new;
+    }
+    let [(stream_a, dial_a, reconnect_a), (stream_b, dial_b, reconnect_b)] = slots;
+    for (conn, stream, dial, reconnect) in [
+        (Connection::A, &*stream_a, dial_a, reconnect_a),
+        (Connection::B, &*stream_b, dial_b, reconnect_b),
+    ] {
+        if wants(new, conn) && stream.is_none() && dial.is_none() && reconnect.is_none() {
+            *dial = Some(Dial::new(conn, source, streams));
         }
     }
+    settle(new, active, stream_a, stream_b, out_map, park).await;
 
+    telemetry::mode_transition(&format!("{old:?}"), &format!("{new:?}"));
     new
 }
 
-async fn open_stream(
-    stream: &mut Option<DynDepth>,
-    currency_pairs: &'static [&'static str],
-    life_span: (NaiveTime, NaiveTime),
+/// Whether `mode` keeps `conn` open.
+fn wants(mode: Mode, conn: Connection) -> bool {
+    match conn {
+        Connection::A => mode != Mode::OnlyB,
+        Connection::B => mode != Mode::OnlyA,
+    }
+}
+
+/// Makes the connection `mode` wants forwarding primary once it is open,
+/// then closes the one `mode` no longer wants.
+///
+/// The park holds the standby's updates: it is flushed when the standby
+/// takes over, and cleared when the connection already forwarding stays
+/// primary. Until the incoming connection is open, the outgoing one stays
+/// up and keeps forwarding. While nothing forwards yet, as at startup, A is
+/// preferred and B is used if only B is open.
+async fn settle(
+    mode: Mode,
+    active: &mut Option<Active>,
+    stream_a: &mut Option<LiveStream>,
+    stream_b: &mut Option<LiveStream>,
+    out_map: &mut HashMap<String, mpsc::Sender<RouterMessage>>,
+    park: &mut HashMap<String, VecDeque<DepthUpdate>>,
 ) {
+    let (a_open, b_open) = (stream_a.is_some(), stream_b.is_some());
+    let is_open = |side: Active| match side {
+        Active::A => a_open,
+        Active::B => b_open,
+    };
+    let current = active.unwrap_or(Active::A);
+    let primary = match mode {
+        Mode::OnlyA => Active::A,
+        Mode::OnlyB => Active::B,
+        // Either may forward; the current one stays while it is open.
+        Mode::BothAB if is_open(current) => current,
+        Mode::BothAB => current.other(),
+    };
+    if !is_open(primary) {
+        return;
+    }
+    if *active != Some(primary) {
+        flush_park(out_map, park).await;
+        *active = Some(primary);
+    } else if mode != Mode::BothAB {
+        clear_park(park);
+    }
+    match mode {
+        Mode::OnlyA => *stream_b = None,
+        Mode::OnlyB => *stream_a = None,
+        Mode::BothAB => {}
+    }
+}
+
+/// Opens `conn` if it isn't, giving up after [`OPEN_TIMEOUT`].
+async fn open_stream(
+    conn: Connection,
+    stream: &mut Option<LiveStream>,
+    source: &Arc<dyn DepthSource>,
+    streams: &[(String, DepthStream)],
+) -> Result<(), Error> {
     if stream.is_none() {
-        let mut builder = TimedStream {
-            currency_pairs,
-            life_span,
-        };
-        if let Ok(s) = builder.init_stream().await {
-            *stream = Some(Box::pin(s));
+        match Dial::new(conn, source, streams).open.await {
+            Ok(live) => {
+                telemetry::ws_connect(conn, true);
+                *stream = Some(live);
+            }
+            Err(e) => {
+                telemetry::ws_connect(conn, false);
+                warn!(connection=?conn, error=%e, "Router: failed to open WS connection");
+                return Err(e);
+            }
         }
     }
+    Ok(())
+}
+
+/// Reports the outcome of startup to `generate_orderbooks`, once.
+fn report_started(
+    started: &mut Option<oneshot::Sender<Result<(), Error>>>,
+    result: Result<(), Error>,
+) {
+    if let Some(tx) = started.take() {
+        let _ = tx.send(result);
+    }
 }
 
 async fn flush_park(
-    out_map: &mut HashMap<String, mpsc::Sender<DepthUpdate>>,
+    out_map: &mut HashMap<String, mpsc::Sender<RouterMessage>>,
     park: &mut HashMap<String, VecDeque<DepthUpdate>>,
 ) {
     for (sym, buf) in park.iter_mut() {
         if let Some(tx) = out_map.get(sym) {
             while let Some(du) = buf.pop_front() {
-                let _ = tx.send(du).await;
+                let _ = tx.send(RouterMessage::Update(du)).await;
             }
         } else {
             buf.clear();
         }
+        telemetry::park_len(sym, 0);
+    }
+}
+
+/// Drops every parked update.
+fn clear_park(park: &mut HashMap<String, VecDeque<DepthUpdate>>) {
+    for (sym, buf) in park.iter_mut() {
+        buf.clear();
+        telemetry::park_len(sym, 0);
+    }
+}
+
+/// Reacts to `conn` ending on its own.
+///
+/// If it was forwarding and the other connection is open, that one takes
+/// over with what it parked. Otherwise every routed symbol is sent
+/// [`RouterMessage::ConnectionLost`] and returned. A standby's parked
+/// updates now have a hole and are discarded.
+#[allow(clippy::too_many_arguments)]
+async fn connection_lost(
+    conn: Connection,
+    active: &mut Option<Active>,
+    other_open: bool,
+    out_map: &mut HashMap<String, mpsc::Sender<RouterMessage>>,
+    park: &mut HashMap<String, VecDeque<DepthUpdate>>,
+    prev_u_by_sym: &mut HashMap<String, u64>,
+) -> Vec<String> {
+    let forwarding = match active {
+        Some(Active::B) => Connection::B,
+        _ => Connection::A,
+    };
+    if conn != forwarding {
+        clear_park(park);
+        return Vec::new();
+    }
+    if other_open {
+        info!(connection=?conn, "Router: failing over to the standby connection");
+        flush_park(out_map, park).await;
+        *active = active.map(Active::other);
+        return Vec::new();
+    }
+
+    prev_u_by_sym.clear();
+    let mut resync = Vec::with_capacity(out_map.len());
+    for (sym, tx) in out_map.iter() {
+        if tx.send(RouterMessage::ConnectionLost).await.is_ok() {
+            resync.push(sym.clone());
+        }
+    }
+    resync.sort();
+    resync
+}
+
+/// Schedules a reconnect for every connection `mode` wants open that isn't,
+/// and cancels those `mode` no longer wants. `slots` are A's and B's stream,
+/// dial in flight and pending reconnect.
+fn schedule_reconnects(
+    mode: Mode,
+    slots: [(&Option<LiveStream>, &Option<Dial>, &mut Option<Reconnect>); 2],
+    policy: &RetryPolicy,
+) {
+    let [a, b] = slots;
+    for (wanted, (stream, dial, reconnect)) in [(wants(mode, Connection::A), a), (wants(mode, Connection::B), b)] {
+        if !wanted || stream.is_some() {
+            *reconnect = None;
+        } else if reconnect.is_none() && dial.is_none() {
+            *reconnect = Some(Reconnect::new(policy));
+        }
+    }
+}
+
+/// Starts the next reconnect attempt for `conn`, resubscribing to all of
+/// `streams`.
+fn redial(
+    conn: Connection,
+    pending: &mut Option<Reconnect>,
+    source: &Arc<dyn DepthSource>,
+    streams: &[(String, DepthStream)],
+) -> Option<Dial> {
+    let state = pending.as_mut()?;
+    state.attempts += 1;
+    Some(Dial::new(conn, source, streams))
+}
+
+/// Handles the outcome of a background open of `conn`.
+///
+/// A reconnect attempt (`pending` set) reports a [`ConnectionEvent`]; on
+/// failure the next attempt is scheduled per `policy`, or `pending` is
+/// cleared once it gives up. A connection `mode` no longer wants is closed
+/// again.
+///
+/// Before the first connection is up, the open (or a failure after which
+/// neither `conn` nor the other connection, per `other_up`, will be tried
+/// again) is reported through `started` instead. Returns whether startup
+/// failed for good.
+#[allow(clippy::too_many_arguments)]
+async fn opened_stream(
+    conn: Connection,
+    mode: Mode,
+    opened: Result<LiveStream, Error>,
+    dialed: &[(String, DepthStream)],
+    stream: &mut Option<LiveStream>,
+    pending: &mut Option<Reconnect>,
+    streams: &[(String, DepthStream)],
+    next_request_id: &mut u64,
+    policy: &RetryPolicy,
+    events: &broadcast::Sender<ConnectionEvent>,
+    started: &mut Option<oneshot::Sender<Result<(), Error>>>,
+    other_up: bool,
+) -> bool {
+    if !wants(mode, conn) {
+        // The mode moved on while it was dialing.
+        if let Ok(live) = opened {
+            live.close().await;
+        }
+        return false;
+    }
+    match opened {
+        Ok(live) => {
+            telemetry::ws_connect(conn, true);
+            sync_streams(live.control.clone(), dialed, streams, next_request_id).await;
+            *stream = Some(live);
+            report_started(started, Ok(()));
+            let Some(state) = pending.take() else {
+                return false;
+            };
+            telemetry::ws_reconnect(conn);
+            let attempts = state.attempts;
+            let downtime = state.dropped.elapsed();
+            info!(connection=?conn, attempts, ?downtime, "Router: reconnected");
+            let _ = events.send(ConnectionEvent::Reconnected {
+                connection: conn,
+                attempts,
+                downtime,
+            });
+            false
+        }
+        Err(e) => {
+            telemetry::ws_connect(conn, false);
+            let Some(state) = pending.as_mut() else {
+                // `schedule_reconnects` retries it from here.
+                warn!(connection=?conn, error=%e, "Router: failed to open WS connection");
+                return false;
+            };
+            let attempts = state.attempts;
+            let gave_up = policy.max_attempts.is_some_and(|max| attempts >= max);
+            if started.is_some() && !other_up && (gave_up || !e.is_retryable()) {
+                error!(connection=?conn, error=%e, attempts, "Router: no WS connection could be opened");
+                *pending = None;
+                report_started(started, Err(e));
+                return true;
+            }
+            if gave_up {
+                error!(connection=?conn, error=%e, attempts, "Router: reconnect failed; giving up until the next mode change");
+                *pending = None;
+            } else {
+                let delay = policy.delay(attempts + 1, None);
+                warn!(connection=?conn, error=%e, attempts, ?delay, "Router: reconnect failed; retrying");
+                state.next_at = tokio::time::Instant::now() + delay;
+            }
+            let _ = events.send(ConnectionEvent::ReconnectFailed {
+                connection: conn,
+                attempts,
+                error: Arc::new(e),
+                gave_up,
+            });
+            false
+        }
+    }
+}
+
+/// Subscribes a connection opened with `dialed` to the streams added since,
+/// and unsubscribes it from those removed.
+async fn sync_streams(
+    control: Option<mpsc::Sender<String>>,
+    dialed: &[(String, DepthStream)],
+    streams: &[(String, DepthStream)],
+    next_request_id: &mut u64,
+) {
+    let Some(control) = control else {
+        return;
+    };
+    let missing = |from: &[(String, DepthStream)], other: &[(String, DepthStream)]| -> Vec<String> {
+        from.iter()
+            .filter(|s| !other.contains(s))
+            .map(|(sym, stream)| WsSource::stream_name(sym, *stream))
+            .collect()
+    };
+    for (method, names) in [
+        ("SUBSCRIBE", missing(streams, dialed)),
+        ("UNSUBSCRIBE", missing(dialed, streams)),
+    ] {
+        if names.is_empty() {
+            continue;
+        }
+        let request = WsSource::control_message(method, &names, *next_request_id);
+        *next_request_id += 1;
+        if let Err(e) = control.send(request).await {
+            warn!(error=%e, "Router: WS control channel closed");
+        }
     }
 }
 
 async fn rout_mode(
     switch_cutoff: (NaiveTime, NaiveTime),
+    overlap: StdDur,
     ctrl_tx: tokio::sync::watch::Sender<Mode>,
+    mut shutdown_rx: watch::Receiver<bool>,
 ) {
     let (cut_a, cut_b) = switch_cutoff;
-    let win_a_start = sub_secs_wrap(cut_a, 3);
-    let win_b_start = sub_secs_wrap(cut_b, 3);
+    let overlap_secs = overlap.as_secs() as i64;
+    let win_a_start = sub_secs_wrap(cut_a, overlap_secs);
+    let win_b_start = sub_secs_wrap(cut_b, overlap_secs);
 
     let mut last_sent: Option<Mode> = Some(Mode::OnlyA);
 
@@ -407,7 +1002,10 @@ async fn rout_mode(
             last_sent = Some(new_mode);
         }
         // Tick roughly once per second; adjust as needed
-        sleep(StdDur::from_millis(250)).await;
+        tokio::select! {
+            _ = sleep(StdDur::from_millis(250)) => {}
+            _ = shutdown_requested(&mut shutdown_rx) => return,
+        }
     }
 }
 
@@ -0,0 +1,194 @@
+use futures_util::future::BoxFuture;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn};
+
+use crate::config::DepthStream;
+use crate::error::Error;
+use crate::latency::Connection;
+use crate::ob_manager::order_book::CombinedDepthUpdate;
+use crate::router::streaming::LiveStream;
+use crate::telemetry;
+
+/// Where the router's A and B connections get their depth events from.
+///
+/// [`WsSource`](crate::WsSource) dials Binance. [`ChannelSource`] is fed in
+/// memory and [`ReplaySource`] plays back a recording, so the router's A/B
+/// handover and the book tasks can run in tests and simulations; pass them
+/// to [`generate_orderbooks_with`](crate::generate_orderbooks_with).
+pub trait DepthSource: Send + Sync {
+    /// Opens connection `conn`, subscribed to `streams` (upper-case symbol
+    /// and depth stream each).
+    fn open<'a>(
+        &'a self,
+        conn: Connection,
+        streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>>;
+}
+
+/// In-memory source: events sent for a connection reach it while it is open.
+///
+/// Like a real socket, a connection that isn't open misses what is sent.
+/// [`streams`](Self::streams) tells what each connection was opened with.
+///
+/// ```
+/// use binance_stream_handler::{ChannelSource, Connection};
+///
+/// let source = ChannelSource::new(1024);
+/// let env = serde_json::from_str(
+///     r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1,"s":"BTCUSDT",
+///         "U":10,"u":12,"pu":9,"b":[["100.0","1.5"]],"a":[]}}"#,
+/// )
+/// .unwrap();
+/// // Nothing is connected yet, so nobody receives it.
+/// assert_eq!(source.send(Connection::A, env), 0);
+/// ```
+#[derive(Debug)]
+pub struct ChannelSource {
+    a: broadcast::Sender<CombinedDepthUpdate>,
+    b: broadcast::Sender<CombinedDepthUpdate>,
+    // Streams of the last open of A and B.
+    opened: Mutex<[Vec<(String, DepthStream)>; 2]>,
+}
+
+impl ChannelSource {
+    /// `cap` events may queue per open connection before the oldest are lost.
+    pub fn new(cap: usize) -> Self {
+        Self {
+            a: broadcast::channel(cap).0,
+            b: broadcast::channel(cap).0,
+            opened: Mutex::default(),
+        }
+    }
+
+    /// Delivers `env` on `conn`; returns how many open connections got it.
+    pub fn send(&self, conn: Connection, env: CombinedDepthUpdate) -> usize {
+        self.sender(conn).send(env).unwrap_or(0)
+    }
+
+    /// Streams `conn` was last opened with, empty if it never was.
+    pub fn streams(&self, conn: Connection) -> Vec<(String, DepthStream)> {
+        self.opened.lock().unwrap()[conn as usize].clone()
+    }
+
+    fn sender(&self, conn: Connection) -> &broadcast::Sender<CombinedDepthUpdate> {
+        match conn {
+            Connection::A => &self.a,
+            Connection::B => &self.b,
+        }
+    }
+}
+
+impl DepthSource for ChannelSource {
+    fn open<'a>(
+        &'a self,
+        conn: Connection,
+        streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>> {
+        self.opened.lock().unwrap()[conn as usize] = streams.to_vec();
+        let rx = self.sender(conn).subscribe();
+        let events = futures_util::stream::unfold(rx, move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(env) => return Some((env, rx)),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(connection=?conn, skipped=n, "Channel source lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Box::pin(std::future::ready(Ok(LiveStream::new(events))))
+    }
+}
+
+/// Plays back a recording: one combined-stream message
+/// (`{"stream":…,"data":…}`) per line, as a WebSocket delivers them.
+///
+/// Every connection that opens replays the file from the start. By default
+/// lines are sent as fast as the router takes them; [`ReplaySource::paced`]
+/// spaces them by their event time `E` instead. At the end of the file the
+/// connection stays open but quiet, so the router doesn't reconnect and
+/// replay it again.
+#[derive(Debug, Clone)]
+pub struct ReplaySource {
+    path: PathBuf,
+    speed: Option<f64>,
+}
+
+impl ReplaySource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            speed: None,
+        }
+    }
+
+    /// Sleeps between events for the gap in their `E`, divided by `speed`
+    /// (`1.0` is real time, `10.0` ten times faster).
+    pub fn paced(mut self, speed: f64) -> Self {
+        self.speed = Some(speed).filter(|s| *s > 0.0);
+        self
+    }
+}
+
+impl DepthSource for ReplaySource {
+    fn open<'a>(
+        &'a self,
+        conn: Connection,
+        _streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>> {
+        Box::pin(async move {
+            let file = tokio::fs::File::open(&self.path)
+                .await
+                .map_err(|source| Error::Io {
+                    symbol: None,
+                    source,
+                })?;
+            let mut lines = tokio::io::BufReader::new(file).lines();
+            let speed = self.speed;
+            let (tx, rx) = mpsc::channel::<CombinedDepthUpdate>(1024);
+
+            let reader = tokio::spawn(async move {
+                let mut last_event_time: Option<u64> = None;
+                loop {
+                    let line = match lines.next_line().await {
+                        Ok(Some(line)) => line,
+                        Ok(None) => break,
+                        Err(e) => {
+                            warn!(connection=?conn, error=%e, "Replay read failed");
+                            break;
+                        }
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let env = match serde_json::from_str::<CombinedDepthUpdate>(&line) {
+                        Ok(env) => env,
+                        Err(e) => {
+                            telemetry::parse_failure();
+                            warn!(error=%e, "Failed to parse replayed message; skipping");
+                            continue;
+                        }
+                    };
+                    if let (Some(speed), Some(prev)) = (speed, last_event_time) {
+                        let gap_ms = env.data.E.saturating_sub(prev) as f64 / speed;
+                        tokio::time::sleep(Duration::from_secs_f64(gap_ms / 1_000.0)).await;
+                    }
+                    last_event_time = Some(env.data.E).filter(|&e| e > 0).or(last_event_time);
+                    if tx.send(env).await.is_err() {
+                        return;
+                    }
+                }
+                debug!(connection=?conn, "Replay finished");
+                tx.closed().await;
+            });
+
+            Ok(LiveStream::with_reader(Box::pin(ReceiverStream::new(rx)), reader))
+        })
+    }
+}
@@ -1,90 +1,260 @@
-use chrono::NaiveTime;
+use futures_util::future::BoxFuture;
 use futures_util::SinkExt;
 use futures_util::Stream;
 use futures_util::StreamExt;
+use serde::Deserialize;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::net::TcpStream;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message;
-use tracing::{debug, error, info, trace, warn};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
 
+use crate::config::{DepthStream, StreamConfig};
+use crate::error::Error;
+use crate::latency::{Connection, ReceiveStamp};
+use crate::telemetry;
 use crate::ob_manager::order_book::CombinedDepthUpdate;
+use crate::router::source::DepthSource;
 
-pub struct TimedStream {
-    pub currency_pairs: &'static [&'static str],
-    pub life_span: (NaiveTime, NaiveTime),
+pub(crate) type DynDepth = Pin<Box<dyn Stream<Item = CombinedDepthUpdate> + Send>>;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// An open router connection: decoded events and, for WebSockets, the
+/// socket's control channel and reader task.
+///
+/// Dropping it (or calling [`LiveStream::close`]) makes a WS reader send a
+/// Close frame and exit.
+pub struct LiveStream {
+    pub(crate) events: DynDepth,
+    pub(crate) control: Option<mpsc::Sender<String>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl LiveStream {
+    /// A connection backed by any event stream, for custom [`DepthSource`]s.
+    ///
+    /// It has no control channel, so runtime SUBSCRIBE/UNSUBSCRIBE requests
+    /// are not forwarded; the router still only routes subscribed symbols.
+    /// Events without a receive stamp are stamped when the router takes
+    /// them, and spot partials get their symbol from the stream name.
+    pub fn new(events: impl Stream<Item = CombinedDepthUpdate> + Send + 'static) -> Self {
+        Self {
+            events: Box::pin(events),
+            control: None,
+            reader: None,
+        }
+    }
+
+    pub(crate) fn with_reader(events: DynDepth, reader: JoinHandle<()>) -> Self {
+        Self {
+            events,
+            control: None,
+            reader: Some(reader),
+        }
+    }
+
+    /// Closes the connection and waits for its reader task, if any.
+    pub async fn close(self) {
+        let LiveStream {
+            events,
+            control,
+            reader,
+        } = self;
+        drop(control);
+        drop(events);
+        if let Some(reader) = reader {
+            if let Err(e) = reader.await {
+                warn!(error=%e, "Stream reader task failed");
+            }
+        }
+    }
+}
+
+/// Reply Binance sends to a SUBSCRIBE/UNSUBSCRIBE request.
+#[derive(Debug, Deserialize)]
+struct CommandReply {
+    id: u64,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+/// Binance combined-stream WebSocket at [`StreamConfig::ws_base_url`]; the
+/// source [`generate_orderbooks`](crate::generate_orderbooks) uses.
+#[derive(Debug, Clone)]
+pub struct WsSource {
+    ws_base_url: String,
 }
 
-impl TimedStream {
-    pub async fn init_stream(
-        &self,
-    ) -> Result<impl Stream<Item = CombinedDepthUpdate> + Send + 'static, Box<dyn std::error::Error>>
-    {
-        let lower: Vec<String> = self
-            .currency_pairs
-            .iter()
-            .map(|s| s.to_lowercase())
-            .collect();
-        let currency_lower: Vec<&str> = lower.iter().map(|s| s.as_str()).collect();
-
-        let ws_url = Self::create_ws_url(&currency_lower);
-        let stream = Self::streaming(ws_url).await?;
-        Ok(stream)
+impl DepthSource for WsSource {
+    fn open<'a>(
+        &'a self,
+        _conn: Connection,
+        streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>> {
+        Box::pin(async move {
+            let stream_names: Vec<String> = streams
+                .iter()
+                .map(|(sym, stream)| Self::stream_name(sym, *stream))
+                .collect();
+
+            let ws_url = Self::create_ws_url(&self.ws_base_url, &stream_names);
+            Self::streaming(ws_url).await
+        })
+    }
+}
+
+impl WsSource {
+    pub fn new(config: &StreamConfig) -> Self {
+        Self {
+            ws_base_url: config.ws_base_url().to_string(),
+        }
     }
 
-    pub async fn streaming(
-        url: String,
-    ) -> Result<impl Stream<Item = CombinedDepthUpdate> + Send + 'static, Box<dyn std::error::Error>>
-    {
+
+    /// Connects and spawns the WS reader.
+    ///
+    /// Returns the decoded depth events plus a sender for raw text frames
+    /// (SUBSCRIBE/UNSUBSCRIBE requests) to write on the same socket.
+    pub async fn streaming(url: String) -> Result<LiveStream, Error> {
         info!("Connecting to {url} ...");
-        let (mut ws, _resp) = tokio_tungstenite::connect_async(&url).await?;
+        let (mut ws, _resp) = tokio_tungstenite::connect_async(&url)
+            .await
+            .map_err(Error::ws_handshake)?;
         info!("Connected. Waiting for messages...");
 
         let (tx, rx) = mpsc::channel::<CombinedDepthUpdate>(1024);
+        let (ctl_tx, mut ctl_rx) = mpsc::channel::<String>(64);
+
+        let reader = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    msg_res = ws.next() => {
+                        let Some(msg_res) = msg_res else { break };
+                        match msg_res {
+                            Ok(Message::Text(txt)) => {
+                                let received = ReceiveStamp::now();
+                                match serde_json::from_str::<CombinedDepthUpdate>(&txt) {
+                                    Ok(mut env) => {
+                                        env.data.received = Some(received);
+                                        if let Err(e) = tx.send(env).await {
+                                            warn!(error=%e, "WS->internal channel closed; WS reader exiting");
+                                            close_ws(&mut ws).await;
+                                            return;
+                                        }
+                                    }
+                                    Err(e) => match serde_json::from_str::<CommandReply>(&txt) {
+                                        Ok(CommandReply { id, error: None }) => {
+                                            debug!(id, "Subscription request acknowledged");
+                                        }
+                                        Ok(CommandReply { id, error: Some(err) }) => {
+                                            warn!(id, error=%err, "Subscription request rejected");
+                                        }
+                                        Err(_) => {
+                                            telemetry::parse_failure();
+                                            warn!(error=%e, "Failed to parse CombinedDepthUpdate; dropping WS message");
+                                        }
+                                    },
+                                }
+                            }
+                            Ok(Message::Ping(payload)) => {
+                                if let Err(e) = ws.send(Message::Pong(payload)).await {
+                                warn!(error=%e, "Failed to send Pong; WS reader exiting");
+                                }
+                            }
+                            Ok(Message::Pong(_)) => {
 
-        tokio::spawn(async move {
-            while let Some(msg_res) = ws.next().await {
-                match msg_res {
-                    Ok(Message::Text(txt)) => {
-                        match serde_json::from_str::<CombinedDepthUpdate>(&txt) {
-                            Ok(env) => { if let Err(e) = tx.send(env).await {
-                                warn!(error=%e, "WS->internal channel closed; WS reader exiting");
-                            } }
+                            }
+                            Ok(Message::Close(frame)) => {
+                                info!(?frame, "WS closed by server");
+                                break;
+                            }
+                            Ok(_) => (),
                             Err(e) => {
-                                warn!(error=%e, "Failed to parse CombinedDepthUpdate; dropping WS message");
+                                warn!(error=%e, "WS read failed");
+                                break;
                             }
                         }
                     }
-                    Ok(Message::Ping(payload)) => {
-                        if let Err(e) = ws.send(Message::Pong(payload)).await {
-                        warn!(error=%e, "Failed to send Pong; WS reader exiting");
+                    request = ctl_rx.recv() => match request {
+                        Some(request) => {
+                            debug!(%request, "Sending WS control message");
+                            if let Err(e) = ws.send(Message::Text(request)).await {
+                                warn!(error=%e, "Failed to send WS control message");
+                            }
                         }
-                    }
-                    Ok(Message::Pong(_)) => {
-
-                    }
-                    Ok(Message::Close(_)) => break,
-                    _ => (),
+                        None => {
+                            // Connection released by the router
+                            close_ws(&mut ws).await;
+                            info!("WS connection closed");
+                            return;
+                        }
+                    },
                 }
             }
             warn!("WS reader task ended");
         });
 
-        Ok(ReceiverStream::new(rx))
+        Ok(LiveStream {
+            events: Box::pin(ReceiverStream::new(rx)),
+            control: Some(ctl_tx),
+            reader: Some(reader),
+        })
     }
 
-    pub fn create_ws_url(currency_pairs: &Vec<&str>) -> String {
-        let stream_spec = "@depth@100ms";
-        let base_url = "wss://fstream.binance.com/stream?streams=";
+    /// Binance stream name, e.g. `adausdt@depth@100ms`.
+    pub fn stream_name(symbol: &str, stream: DepthStream) -> String {
+        format!("{}{}", symbol.to_lowercase(), stream.spec())
+    }
+
+    /// Builds a SUBSCRIBE or UNSUBSCRIBE request for the given stream names.
+    pub fn control_message(method: &str, stream_names: &[String], id: u64) -> String {
+        serde_json::json!({ "method": method, "params": stream_names, "id": id }).to_string()
+    }
+
+    pub fn create_ws_url(ws_base_url: &str, stream_names: &[String]) -> String {
+        if stream_names.is_empty() {
+            // Nothing to subscribe yet; streams are added later via SUBSCRIBE.
+            return format!("{ws_base_url}/stream");
+        }
 
-        let mut url = String::from(base_url);
-        for (i, pair) in currency_pairs.iter().enumerate() {
-            let insert_str = format!("{}{}", pair, stream_spec);
+        let mut url = format!("{ws_base_url}/stream?streams=");
+        for (i, name) in stream_names.iter().enumerate() {
             if i > 0 {
-                url.push_str("/")
+                url.push('/')
             }
-            url.push_str(&insert_str);
+            url.push_str(name);
         }
 
         url
     }
 }
+
+/// `"bnbbtc@depth5@100ms"` → `"BNBBTC"`.
+pub(crate) fn symbol_from_stream(stream: &str) -> String {
+    stream
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase()
+}
+
+/// Sends a normal Close frame and waits briefly for the server's reply.
+async fn close_ws(ws: &mut WsStream) {
+    let frame = CloseFrame {
+        code: CloseCode::Normal,
+        reason: "client shutdown".into(),
+    };
+    if let Err(e) = ws.close(Some(frame)).await {
+        debug!(error=%e, "Failed to send Close frame");
+        return;
+    }
+    let drain = async { while let Some(Ok(_)) = ws.next().await {} };
+    let _ = tokio::time::timeout(Duration::from_secs(2), drain).await;
+}
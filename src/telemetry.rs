@@ -0,0 +1,191 @@
+//! Prometheus metrics, recorded through the `metrics` facade.
+//!
+//! Without the `metrics` feature every recorder below compiles to nothing.
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::error::Error;
+use crate::latency::Connection;
+use crate::ob_manager::order_book::ResyncCause;
+
+#[cfg(feature = "metrics")]
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+
+/// Serves `/metrics` on `addr` in the Prometheus text format.
+///
+/// Installs the global `metrics` recorder, so call it once, from inside the
+/// Tokio runtime, before [`generate_orderbooks`](crate::generate_orderbooks).
+///
+/// ```no_run
+/// # #[tokio::main] async fn main() {
+/// binance_stream_handler::serve_metrics(([127, 0, 0, 1], 9000).into()).unwrap();
+/// # }
+/// ```
+#[cfg(feature = "metrics")]
+pub fn serve_metrics(
+    addr: std::net::SocketAddr,
+) -> Result<(), metrics_exporter_prometheus::BuildError> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()?;
+
+    describe_counter!("binance_book_updates_applied_total", "Depth updates applied, per symbol");
+    describe_counter!("binance_book_updates_dropped_total", "Stale depth updates dropped, per symbol");
+    describe_counter!("binance_book_resyncs_total", "Book resyncs, per symbol and cause");
+    describe_histogram!("binance_snapshot_fetch_seconds", "REST depth snapshot latency");
+    describe_counter!("binance_snapshot_failures_total", "Failed REST depth snapshots, per symbol and kind");
+    describe_gauge!("binance_rest_used_weight", "Last X-MBX-USED-WEIGHT-1M reported by the exchange");
+    describe_counter!("binance_router_mode_transitions_total", "Router A/B mode transitions");
+    describe_gauge!("binance_router_park_len", "Updates parked for the standby connection, per symbol");
+    describe_gauge!("binance_router_channel_fill", "Queued updates in the router -> book channel, per symbol");
+    describe_counter!("binance_ws_connects_total", "WebSocket connection attempts, per connection and result");
+    describe_counter!("binance_ws_reconnects_total", "Dropped WebSocket connections reopened, per connection");
+    describe_counter!("binance_ws_parse_failures_total", "WebSocket text frames that failed to parse");
+    Ok(())
+}
+
+/// Per-update counters of one book task, labelled once when the task starts.
+///
+/// Handles bind to the recorder installed at that point, which is why
+/// [`serve_metrics`] has to run first.
+pub(crate) struct BookMetrics {
+    #[cfg(feature = "metrics")]
+    applied: metrics::Counter,
+    #[cfg(feature = "metrics")]
+    dropped: metrics::Counter,
+}
+
+impl BookMetrics {
+    pub(crate) fn new(symbol: &str) -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            applied: counter!("binance_book_updates_applied_total", "symbol" => symbol.to_string()),
+            #[cfg(feature = "metrics")]
+            dropped: counter!("binance_book_updates_dropped_total", "symbol" => symbol.to_string()),
+        }
+    }
+
+    pub(crate) fn update_applied(&self) {
+        #[cfg(feature = "metrics")]
+        self.applied.increment(1);
+    }
+
+    pub(crate) fn update_dropped(&self) {
+        #[cfg(feature = "metrics")]
+        self.dropped.increment(1);
+    }
+}
+
+/// Per-update gauges the router keeps for each routed symbol.
+pub(crate) struct RouterGauges {
+    #[cfg(feature = "metrics")]
+    channel_fill: metrics::Gauge,
+    #[cfg(feature = "metrics")]
+    park_len: metrics::Gauge,
+}
+
+impl RouterGauges {
+    pub(crate) fn new(symbol: &str) -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            channel_fill: gauge!("binance_router_channel_fill", "symbol" => symbol.to_string()),
+            #[cfg(feature = "metrics")]
+            park_len: gauge!("binance_router_park_len", "symbol" => symbol.to_string()),
+        }
+    }
+
+    pub(crate) fn channel_fill<T>(&self, tx: &mpsc::Sender<T>) {
+        #[cfg(feature = "metrics")]
+        self.channel_fill.set((tx.max_capacity() - tx.capacity()) as f64);
+    }
+
+    pub(crate) fn park_len(&self, len: usize) {
+        #[cfg(feature = "metrics")]
+        self.park_len.set(len as f64);
+    }
+}
+
+pub(crate) fn resync(symbol: &str, cause: ResyncCause) {
+    #[cfg(feature = "metrics")]
+    counter!(
+        "binance_book_resyncs_total",
+        "symbol" => symbol.to_string(),
+        "cause" => cause.as_str()
+    )
+    .increment(1);
+}
+
+pub(crate) fn snapshot_fetched(symbol: &str, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    histogram!("binance_snapshot_fetch_seconds", "symbol" => symbol.to_string())
+        .record(elapsed.as_secs_f64());
+}
+
+pub(crate) fn snapshot_failed(symbol: &str, error: &Error) {
+    #[cfg(feature = "metrics")]
+    {
+        let kind = match error {
+            Error::RateLimited { .. } => "rate_limited",
+            Error::Http { .. } => "http",
+            Error::Transport { .. } => "transport",
+            Error::Decode { .. } => "decode",
+            _ => "other",
+        };
+        counter!(
+            "binance_snapshot_failures_total",
+            "symbol" => symbol.to_string(),
+            "kind" => kind
+        )
+        .increment(1);
+    }
+}
+
+pub(crate) fn rest_used_weight(weight: u32) {
+    #[cfg(feature = "metrics")]
+    gauge!("binance_rest_used_weight").set(f64::from(weight));
+}
+
+pub(crate) fn mode_transition(from: &str, to: &str) {
+    #[cfg(feature = "metrics")]
+    counter!(
+        "binance_router_mode_transitions_total",
+        "from" => from.to_string(),
+        "to" => to.to_string()
+    )
+    .increment(1);
+}
+
+pub(crate) fn park_len(symbol: &str, len: usize) {
+    #[cfg(feature = "metrics")]
+    gauge!("binance_router_park_len", "symbol" => symbol.to_string()).set(len as f64);
+}
+
+pub(crate) fn ws_connect(conn: Connection, ok: bool) {
+    #[cfg(feature = "metrics")]
+    counter!(
+        "binance_ws_connects_total",
+        "connection" => connection_label(conn),
+        "result" => if ok { "ok" } else { "error" }
+    )
+    .increment(1);
+}
+
+pub(crate) fn ws_reconnect(conn: Connection) {
+    #[cfg(feature = "metrics")]
+    counter!("binance_ws_reconnects_total", "connection" => connection_label(conn)).increment(1);
+}
+
+#[cfg(feature = "metrics")]
+fn connection_label(conn: Connection) -> &'static str {
+    match conn {
+        Connection::A => "a",
+        Connection::B => "b",
+    }
+}
+
+pub(crate) fn parse_failure() {
+    #[cfg(feature = "metrics")]
+    counter!("binance_ws_parse_failures_total").increment(1);
+}
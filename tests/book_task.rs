@@ -0,0 +1,385 @@
+//! Book task sequencing against a `SnapshotFn` mock, fed through a
+//! `ChannelSource`.
+
+mod common;
+
+use binance_stream_handler::{
+    generate_orderbooks_with, ChannelSource, CombinedDepthUpdate, Connection, DepthSnapshot,
+    DepthStream, Error, FeedStatus, Market, RetryPolicy, SnapshotFn, SnapshotPriority,
+    SnapshotProvider, StreamConfig,
+};
+use common::{bid, futures, only_a, partial, snapshots, spot, wait_for_status, wait_for_u};
+use futures_util::future::BoxFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+#[tokio::test]
+async fn drops_updates_older_than_the_snapshot() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(80, 90, 79, "97.0", "5.0"));
+    source.send(Connection::A, futures(91, 99, 90, "96.0", "5.0"));
+    source.send(Connection::A, futures(100, 104, 99, "98.0", "2.0"));
+
+    let ob = wait_for_u(&mut book, 104).await;
+    assert_eq!(ob.snapshot_id, Some(100));
+    assert_eq!(bid(&ob, 98.0), Some(2.0));
+    assert_eq!(bid(&ob, 97.0), None);
+    assert_eq!(bid(&ob, 96.0), None);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn futures_bridge_contains_last_update_id() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    // U <= lastUpdateId <= u
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    source.send(Connection::A, futures(103, 105, 102, "97.0", "3.0"));
+
+    let ob = wait_for_u(&mut book, 105).await;
+    assert_eq!(bid(&ob, 98.0), Some(2.0));
+    assert_eq!(bid(&ob, 97.0), Some(3.0));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn spot_bridge_contains_last_update_id_plus_one() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .market(Market::Spot)
+        .build()
+        .unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    // Ends at lastUpdateId: already in the snapshot.
+    source.send(Connection::A, spot(95, 100, "96.0", "5.0"));
+    // U <= lastUpdateId + 1 <= u
+    source.send(Connection::A, spot(101, 103, "98.0", "2.0"));
+    source.send(Connection::A, spot(104, 106, "97.0", "3.0"));
+
+    let ob = wait_for_u(&mut book, 106).await;
+    assert_eq!(bid(&ob, 96.0), None);
+    assert_eq!(bid(&ob, 98.0), Some(2.0));
+    assert_eq!(bid(&ob, 97.0), Some(3.0));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn spot_update_overlapping_last_u_is_applied() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .market(Market::Spot)
+        .build()
+        .unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    source.send(Connection::A, spot(101, 103, "98.0", "2.0"));
+    // Starts inside what was applied but ends past it, as at a handover.
+    source.send(Connection::A, spot(102, 105, "97.0", "3.0"));
+    // Entirely applied already.
+    source.send(Connection::A, spot(104, 105, "96.0", "4.0"));
+    source.send(Connection::A, spot(106, 107, "95.0", "5.0"));
+
+    let ob = wait_for_u(&mut book, 107).await;
+    assert_eq!(bid(&ob, 97.0), Some(3.0));
+    assert_eq!(bid(&ob, 96.0), None);
+    assert_eq!(bid(&ob, 95.0), Some(5.0));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn sequence_gap_resyncs_and_replays_the_update() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, calls) = snapshots(&[100, 300]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    wait_for_u(&mut book, 102).await;
+
+    // pu 289 skips past last_u 102; the update bridges the next snapshot.
+    source.send(Connection::A, futures(290, 305, 289, "97.0", "3.0"));
+
+    let ob = wait_for_u(&mut book, 305).await;
+    assert_eq!(ob.snapshot_id, Some(300));
+    assert_eq!(bid(&ob, 97.0), Some(3.0));
+    // Rebuilt from the new snapshot, not patched onto the old book.
+    assert_eq!(bid(&ob, 98.0), None);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn rejected_partial_leaves_live_until_the_next_good_one() {
+    let stream = DepthStream::Partial {
+        levels: 5,
+        speed_ms: Some(100),
+    };
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .market(Market::Spot)
+        .depth_stream(stream)
+        .build()
+        .unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, partial(160, &[["98.0", "2.0"]], &[["99.0", "1.0"]]));
+    wait_for_u(&mut book, 160).await;
+    wait_for_status(&mut status, FeedStatus::Live).await;
+
+    // Crossed: the book keeps 160 and stops reporting live.
+    source.send(Connection::A, partial(161, &[["100.0", "2.0"]], &[["99.0", "1.0"]]));
+    wait_for_status(&mut status, FeedStatus::Resyncing).await;
+    assert_eq!(book.borrow().last_u, Some(160));
+
+    source.send(Connection::A, partial(162, &[["97.0", "2.0"]], &[["99.0", "1.0"]]));
+    let ob = wait_for_u(&mut book, 162).await;
+    assert_eq!(bid(&ob, 97.0), Some(2.0));
+    wait_for_status(&mut status, FeedStatus::Live).await;
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn unparsable_snapshot_fails_the_feed_without_panicking() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let provider = SnapshotFn(|_symbol: &str, _limit: u16| -> Result<_, Error> {
+        Ok(DepthSnapshot::new(
+            100,
+            vec![["99.0".into(), "one".into()]],
+            vec![["101.0".into(), "1.0".into()]],
+        ))
+    });
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, Arc::new(provider), source.clone())
+        .await
+        .unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    let failed = tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|h| matches!(h.status, FeedStatus::Failed { .. })),
+    )
+    .await
+    .is_ok();
+    assert!(failed, "feed never failed, at {:?}", status.borrow().status);
+    handle.shutdown().await;
+}
+
+#[test]
+fn unparsable_levels_are_rejected_when_decoding() {
+    let event = serde_json::from_value::<CombinedDepthUpdate>(serde_json::json!({
+        "stream": "btcusdt@depth@100ms",
+        "data": {
+            "e": "depthUpdate", "E": 1, "s": "BTCUSDT",
+            "U": 1, "u": 1, "pu": 0, "b": [["98.0", "2..0"]], "a": []
+        }
+    }));
+    assert!(event.is_err());
+    let snapshot = serde_json::from_value::<DepthSnapshot>(serde_json::json!({
+        "lastUpdateId": 1, "bids": [], "asks": [["x", "1.0"]]
+    }));
+    assert!(snapshot.is_err());
+}
+
+#[tokio::test]
+async fn quiet_feed_goes_stale_and_back_to_live() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .stale_after(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    wait_for_u(&mut book, 102).await;
+    wait_for_status(&mut status, FeedStatus::Live).await;
+
+    wait_for_status(&mut status, FeedStatus::Stale).await;
+
+    source.send(Connection::A, futures(103, 105, 102, "97.0", "3.0"));
+    wait_for_u(&mut book, 105).await;
+    wait_for_status(&mut status, FeedStatus::Live).await;
+    handle.shutdown().await;
+}
+
+/// Serves snapshot 100 once; later fetches wait for `release`, then fail
+/// with a non-retryable 400.
+#[derive(Default)]
+struct FailsAfterFirst {
+    calls: AtomicUsize,
+    release: Notify,
+}
+
+impl SnapshotProvider for FailsAfterFirst {
+    fn snapshot<'a>(
+        &'a self,
+        symbol: &'a str,
+        _limit: u16,
+        _priority: SnapshotPriority,
+    ) -> BoxFuture<'a, Result<DepthSnapshot, Error>> {
+        Box::pin(async move {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Ok(DepthSnapshot::new(
+                    100,
+                    vec![["99.0".into(), "1.0".into()]],
+                    vec![["101.0".into(), "1.0".into()]],
+                ));
+            }
+            self.release.notified().await;
+            Err(Error::Http {
+                symbol: Some(symbol.to_string()),
+                status: 400,
+                code: Some(-1100),
+                message: Some("Illegal characters found in parameter".into()),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn resync_with_a_failing_snapshot_ends_failed() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let provider = Arc::new(FailsAfterFirst::default());
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider.clone(), source.clone())
+        .await
+        .unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    wait_for_u(&mut book, 102).await;
+    wait_for_status(&mut status, FeedStatus::Live).await;
+
+    // pu 110 skips past 102.
+    source.send(Connection::A, futures(111, 113, 110, "97.0", "3.0"));
+    wait_for_status(&mut status, FeedStatus::Resyncing).await;
+
+    provider.release.notify_one();
+    let failed = tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|h| matches!(&h.status, FeedStatus::Failed { reason } if reason.contains("HTTP 400"))),
+    )
+    .await
+    .is_ok();
+    assert!(failed, "feed never failed, at {:?}", status.borrow().status);
+    // The last good book stays published.
+    assert_eq!(book.borrow().last_u, Some(102));
+    assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn crossed_snapshot_is_retried_until_consistent() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .snapshot_retry(RetryPolicy::default().initial_backoff(Duration::from_millis(10)))
+        .build()
+        .unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let provider = SnapshotFn(move |_symbol: &str, _limit: u16| -> Result<_, Error> {
+        // Bid above the ask the first time.
+        let bid = match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => "102.0",
+            _ => "99.0",
+        };
+        Ok(DepthSnapshot::new(
+            100,
+            vec![[bid.into(), "1.0".into()]],
+            vec![["101.0".into(), "1.0".into()]],
+        ))
+    });
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, Arc::new(provider), source.clone())
+        .await
+        .unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    let ob = wait_for_u(&mut book, 102).await;
+    assert_eq!(bid(&ob, 99.0), Some(1.0));
+    assert_eq!(bid(&ob, 102.0), None);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn snapshot_below_min_book_levels_fails_once_retries_run_out() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .min_book_levels(2)
+        .snapshot_retry(
+            RetryPolicy::default()
+                .initial_backoff(Duration::from_millis(10))
+                .max_attempts(Some(2)),
+        )
+        .build()
+        .unwrap();
+    // One level a side.
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    let failed = tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|h| {
+            matches!(&h.status, FeedStatus::Failed { reason } if reason.contains("collapsed to 1 levels"))
+        }),
+    )
+    .await
+    .is_ok();
+    assert!(failed, "feed never failed, at {:?}", status.borrow().status);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn crossing_update_is_rejected_and_resyncs() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, calls) = snapshots(&[100, 300]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut status = handle.feed_status("BTCUSDT").unwrap();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    wait_for_u(&mut book, 102).await;
+
+    // A bid above the 101 ask.
+    source.send(Connection::A, futures(103, 105, 102, "102.0", "1.0"));
+    wait_for_status(&mut status, FeedStatus::Resyncing).await;
+
+    source.send(Connection::A, futures(299, 301, 298, "97.0", "3.0"));
+    let ob = wait_for_u(&mut book, 301).await;
+    assert_eq!(ob.snapshot_id, Some(300));
+    assert_eq!(bid(&ob, 102.0), None);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    handle.shutdown().await;
+}
@@ -0,0 +1,100 @@
+#![allow(dead_code, non_snake_case)]
+
+use binance_stream_handler::{
+    price_to_f64, qty_to_f64, CombinedDepthUpdate, DepthSnapshot, Error, FeedHealth, FeedStatus,
+    OrderBook, SnapshotFn, SnapshotProvider, StreamConfigBuilder,
+};
+use chrono::{Duration as ChronoDur, Timelike, Utc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Cutoffs that keep the mode clock in `OnlyA` for the next hour.
+pub fn only_a(builder: StreamConfigBuilder) -> StreamConfigBuilder {
+    let now = Utc::now().time().with_nanosecond(0).unwrap();
+    builder.switch_cutoffs(now - ChronoDur::hours(1), now + ChronoDur::hours(1))
+}
+
+/// Futures diff event on `btcusdt@depth@100ms` setting one bid level.
+pub fn futures(U: u64, u: u64, pu: u64, bid: &str, qty: &str) -> CombinedDepthUpdate {
+    serde_json::from_value(serde_json::json!({
+        "stream": "btcusdt@depth@100ms",
+        "data": {
+            "e": "depthUpdate", "E": 1000 + u, "T": 999 + u, "s": "BTCUSDT",
+            "U": U, "u": u, "pu": pu, "b": [[bid, qty]], "a": []
+        }
+    }))
+    .unwrap()
+}
+
+/// Spot diff event (no `pu`) on `btcusdt@depth@100ms` setting one bid level.
+pub fn spot(U: u64, u: u64, bid: &str, qty: &str) -> CombinedDepthUpdate {
+    serde_json::from_value(serde_json::json!({
+        "stream": "btcusdt@depth@100ms",
+        "data": {
+            "e": "depthUpdate", "E": 1000 + u, "s": "BTCUSDT",
+            "U": U, "u": u, "b": [[bid, qty]], "a": []
+        }
+    }))
+    .unwrap()
+}
+
+/// Spot top-N message on `btcusdt@depth5@100ms`: no event fields, just
+/// `lastUpdateId` and both sides.
+pub fn partial(last_update_id: u64, bids: &[[&str; 2]], asks: &[[&str; 2]]) -> CombinedDepthUpdate {
+    serde_json::from_value(serde_json::json!({
+        "stream": "btcusdt@depth5@100ms",
+        "data": { "lastUpdateId": last_update_id, "bids": bids, "asks": asks }
+    }))
+    .unwrap()
+}
+
+/// Waits until the feed reports `status`.
+pub async fn wait_for_status(rx: &mut watch::Receiver<FeedHealth>, status: FeedStatus) {
+    let reached = tokio::time::timeout(
+        Duration::from_secs(5),
+        rx.wait_for(|h| h.status == status),
+    )
+    .await
+    .is_ok();
+    assert!(reached, "feed never reached {status:?}, at {:?}", rx.borrow().status);
+}
+
+/// Snapshot mock serving `ids` in turn (the last one repeatedly), with a
+/// 99/101 top of book. The counter tells how many snapshots were fetched.
+pub fn snapshots(ids: &'static [u64]) -> (Arc<dyn SnapshotProvider>, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let provider = SnapshotFn(move |_symbol: &str, _limit: u16| -> Result<_, Error> {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        Ok(DepthSnapshot::new(
+            ids[n.min(ids.len() - 1)],
+            vec![["99.0".into(), "1.0".into()]],
+            vec![["101.0".into(), "1.0".into()]],
+        ))
+    });
+    (Arc::new(provider), calls)
+}
+
+/// Waits until the published book has applied up to `u`.
+pub async fn wait_for_u(rx: &mut watch::Receiver<OrderBook>, u: u64) -> OrderBook {
+    let reached = tokio::time::timeout(
+        Duration::from_secs(5),
+        rx.wait_for(|ob| ob.last_u == Some(u)),
+    )
+    .await
+    .map(|ob| ob.map(|ob| ob.clone()));
+    match reached {
+        Ok(ob) => ob.unwrap(),
+        Err(_) => panic!("book never reached u={u}, at {:?}", rx.borrow().last_u),
+    }
+}
+
+/// Quantity of the bid at `price`, if the book has one.
+pub fn bid(ob: &OrderBook, price: f64) -> Option<f64> {
+    ob.bids
+        .iter()
+        .find(|(p, _)| price_to_f64(**p) == price)
+        .map(|(_, q)| qty_to_f64(*q))
+}
@@ -0,0 +1,136 @@
+//! `StreamConfigBuilder::build` validation.
+
+use binance_stream_handler::{
+    ConfigError, DepthStream, Market, RetryPolicy, StreamConfig, StreamConfigBuilder,
+};
+use chrono::NaiveTime;
+use std::time::Duration;
+
+fn builder() -> StreamConfigBuilder {
+    StreamConfig::builder(["btcusdt", "ETHUSDT"])
+}
+
+fn hm(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+#[test]
+fn defaults_build_and_symbols_are_upper_cased() {
+    let config = builder().build().unwrap();
+    assert_eq!(config.currency_pairs(), ["BTCUSDT", "ETHUSDT"]);
+    assert_eq!(config.market(), Market::UsdMFutures);
+    assert_eq!(config.rest_weight_budget(), 1920);
+}
+
+#[test]
+fn symbols_must_be_valid_and_unique() {
+    let err = StreamConfig::builder(["BTC-USDT"]).build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidSymbol("BTC-USDT".into()));
+    let err = StreamConfig::builder(["btcusdt", "BTCUSDT"]).build().unwrap_err();
+    assert_eq!(err, ConfigError::DuplicateSymbol("BTCUSDT".into()));
+}
+
+#[test]
+fn capacities_must_be_non_zero() {
+    assert_eq!(builder().chan_cap(0).build().unwrap_err(), ConfigError::ZeroCapacity("chan_cap"));
+    assert_eq!(builder().park_cap(0).build().unwrap_err(), ConfigError::ZeroCapacity("park_cap"));
+    assert_eq!(
+        builder().rest_weight_budget(0).build().unwrap_err(),
+        ConfigError::ZeroCapacity("rest_weight_budget")
+    );
+}
+
+#[test]
+fn base_urls_need_a_matching_scheme_and_lose_trailing_slashes() {
+    let config = builder()
+        .rest_base_url("http://localhost:8080/")
+        .ws_base_url("ws://localhost:9090")
+        .build()
+        .unwrap();
+    assert_eq!(config.rest_base_url(), "http://localhost:8080");
+    assert_eq!(config.ws_base_url(), "ws://localhost:9090");
+
+    let err = builder().rest_base_url("wss://fapi.binance.com").build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidUrl("wss://fapi.binance.com".into()));
+    let err = builder().ws_base_url("wss://").build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidUrl("wss://".into()));
+}
+
+#[test]
+fn depth_streams_must_exist_on_the_market() {
+    let spot_250 = DepthStream::Diff { speed_ms: Some(250) };
+    let err = builder().market(Market::Spot).depth_stream(spot_250).build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidDepthStream(Market::Spot, spot_250));
+    assert!(builder().depth_stream(spot_250).build().is_ok());
+
+    let top_7 = DepthStream::Partial { levels: 7, speed_ms: None };
+    let err = builder().symbol_depth_stream("ETHUSDT", top_7).build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidDepthStream(Market::UsdMFutures, top_7));
+
+    let top_5 = DepthStream::Partial { levels: 5, speed_ms: None };
+    let err = builder().symbol_depth_stream("SOLUSDT", top_5).build().unwrap_err();
+    assert_eq!(err, ConfigError::UnknownSymbol("SOLUSDT".into()));
+}
+
+#[test]
+fn snapshot_depth_follows_the_market_limits() {
+    let err = builder().snapshot_depth(750).build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidSnapshotDepth(Market::UsdMFutures, 750));
+    assert!(builder().market(Market::Spot).snapshot_depth(750).build().is_ok());
+    let err = builder().market(Market::Spot).snapshot_depth(5001).build().unwrap_err();
+    assert_eq!(err, ConfigError::InvalidSnapshotDepth(Market::Spot, 5001));
+}
+
+#[test]
+fn min_book_levels_must_fit_every_stream() {
+    let top_5 = DepthStream::Partial { levels: 5, speed_ms: None };
+    let err = builder()
+        .symbol_depth_stream("ETHUSDT", top_5)
+        .min_book_levels(10)
+        .build()
+        .unwrap_err();
+    assert_eq!(err, ConfigError::MinBookLevelsAboveDepth(10, top_5));
+    assert!(builder().min_book_levels(10).build().is_ok());
+}
+
+#[test]
+fn overlap_must_be_shorter_than_both_windows() {
+    // A gets 00:00-20:00, B 20:00-00:00.
+    let cutoffs = |b: StreamConfigBuilder| b.switch_cutoffs(hm(0, 0), hm(20, 0));
+    assert!(cutoffs(builder()).overlap(Duration::from_secs(3599)).build().is_ok());
+    let over = Duration::from_secs(4 * 3600);
+    assert_eq!(
+        cutoffs(builder()).overlap(over).build().unwrap_err(),
+        ConfigError::InvalidOverlap(over)
+    );
+    assert_eq!(
+        builder().overlap(Duration::from_millis(500)).build().unwrap_err(),
+        ConfigError::InvalidOverlap(Duration::from_millis(500))
+    );
+    assert_eq!(
+        builder().switch_cutoffs(hm(8, 0), hm(8, 0)).build().unwrap_err(),
+        ConfigError::InvalidCutoffs(hm(8, 0), hm(8, 0))
+    );
+}
+
+#[test]
+fn stale_after_and_retry_policies_are_checked() {
+    assert_eq!(
+        builder().stale_after(Duration::ZERO).build().unwrap_err(),
+        ConfigError::InvalidStaleAfter(Duration::ZERO)
+    );
+    let bad = RetryPolicy::default().jitter(2.0);
+    let err = builder().snapshot_retry(bad.clone()).build().unwrap_err();
+    assert_eq!(
+        err,
+        ConfigError::InvalidRetryPolicy {
+            policy: "snapshot_retry",
+            reason: "jitter must be within 0..=1",
+        }
+    );
+    assert_eq!(err.to_string(), "snapshot_retry: jitter must be within 0..=1");
+    assert!(matches!(
+        builder().ws_reconnect(bad).build().unwrap_err(),
+        ConfigError::InvalidRetryPolicy { policy: "ws_reconnect", .. }
+    ));
+}
@@ -0,0 +1,125 @@
+//! Delta and BBO event streams next to the `watch` book.
+
+mod common;
+
+use binance_stream_handler::{
+    generate_orderbooks_with, price_to_f64, qty_to_f64, BookEvent, BookSide, ChannelSource,
+    Connection, OrderBook, StreamConfig,
+};
+use common::{futures, only_a, snapshots, wait_for_u};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+
+/// Waits for the initial snapshot, so that subscribing afterwards only sees
+/// what the updates cause.
+async fn wait_for_snapshot(rx: &mut watch::Receiver<OrderBook>) {
+    tokio::time::timeout(Duration::from_secs(5), rx.wait_for(|ob| ob.snapshot_id.is_some()))
+        .await
+        .expect("no snapshot")
+        .unwrap();
+}
+
+async fn next<T: Clone>(rx: &mut broadcast::Receiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("no event")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn deltas_carry_old_and_new_quantities() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, _) = snapshots(&[100, 300]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    wait_for_snapshot(&mut book).await;
+    let mut events = handle.book_events("BTCUSDT").unwrap();
+
+    // Snapshot holds 99.0 x 1.0: a changed level, a new one, a no-op, a removal.
+    source.send(Connection::A, futures(96, 102, 95, "99.0", "3.0"));
+    source.send(Connection::A, futures(103, 104, 102, "98.0", "2.0"));
+    source.send(Connection::A, futures(105, 106, 104, "98.0", "2.0"));
+    source.send(Connection::A, futures(107, 108, 106, "98.0", "0"));
+    wait_for_u(&mut book, 108).await;
+
+    let mut changes = Vec::new();
+    for (first, u) in [(96, 102), (103, 104), (105, 106), (107, 108)] {
+        let BookEvent::Delta(delta) = next(&mut events).await else {
+            panic!("expected a delta for u={u}");
+        };
+        assert_eq!((delta.first_update_id, delta.final_update_id), (first, u));
+        assert_eq!(delta.event_time, 1000 + u);
+        changes.push(
+            delta
+                .changes
+                .iter()
+                .map(|c| {
+                    assert_eq!(c.side, BookSide::Bid);
+                    (price_to_f64(c.price), qty_to_f64(c.old_qty), qty_to_f64(c.new_qty))
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+    assert_eq!(
+        changes,
+        [
+            vec![(99.0, 1.0, 3.0)],
+            vec![(98.0, 0.0, 2.0)],
+            vec![],
+            vec![(98.0, 2.0, 0.0)],
+        ]
+    );
+
+    // A gap rebuilds the book, which earlier deltas no longer apply to.
+    source.send(Connection::A, futures(290, 305, 289, "97.0", "3.0"));
+    let BookEvent::Reset(reset) = next(&mut events).await else {
+        panic!("expected a reset after the gap");
+    };
+    assert_eq!(reset.snapshot_id, Some(300));
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn bbo_is_published_only_when_the_top_changes() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    wait_for_snapshot(&mut book).await;
+    let mut bbo = handle.bbo_events("BTCUSDT").unwrap();
+    let top = |b: Option<(_, _)>| b.map(|(p, q)| (price_to_f64(p), qty_to_f64(q)));
+
+    // Below the 99.0 best bid: no event.
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    // New best bid.
+    source.send(Connection::A, futures(103, 104, 102, "100.0", "1.0"));
+    // Same price and size again: no event.
+    source.send(Connection::A, futures(105, 106, 104, "100.0", "1.0"));
+    // Size at the top changes.
+    source.send(Connection::A, futures(107, 108, 106, "100.0", "4.0"));
+    // Pulling the best bid exposes 99.0 again.
+    source.send(Connection::A, futures(109, 110, 108, "100.0", "0"));
+
+    let ev = next(&mut bbo).await;
+    assert_eq!(ev.symbol, "BTCUSDT");
+    assert_eq!(top(ev.prev.bid), Some((99.0, 1.0)));
+    assert_eq!(top(ev.new.bid), Some((100.0, 1.0)));
+    assert_eq!(top(ev.new.ask), Some((101.0, 1.0)));
+    assert_eq!((ev.update_id, ev.event_time), (104, Some(1104)));
+
+    let ev = next(&mut bbo).await;
+    assert_eq!(top(ev.new.bid), Some((100.0, 4.0)));
+    assert_eq!(ev.update_id, 108);
+
+    let ev = next(&mut bbo).await;
+    assert_eq!(top(ev.prev.bid), Some((100.0, 4.0)));
+    assert_eq!(top(ev.new.bid), Some((99.0, 1.0)));
+    assert_eq!(ev.update_id, 110);
+
+    wait_for_u(&mut book, 110).await;
+    assert!(matches!(bbo.try_recv(), Err(broadcast::error::TryRecvError::Empty)));
+    handle.shutdown().await;
+}
@@ -0,0 +1,363 @@
+//! Router handover and `ChannelSource` delivery, end to end through the
+//! book tasks.
+
+mod common;
+
+use binance_stream_handler::{
+    generate_orderbooks_with, ChannelSource, CombinedDepthUpdate, Connection, ConnectionEvent,
+    DepthSource, DepthStream, Error, FeedStatus, LiveStream, Market, RetryPolicy, StreamConfig,
+};
+use chrono::{Duration as ChronoDur, Timelike, Utc};
+use common::{bid, futures, only_a, snapshots, wait_for_u};
+use futures_util::future::BoxFuture;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_tungstenite::tungstenite;
+
+/// An event for a symbol nobody subscribed to: the router discards it, but
+/// `send` still tells whether `conn` is open.
+fn probe() -> CombinedDepthUpdate {
+    serde_json::from_value(serde_json::json!({
+        "stream": "ethusdt@depth@100ms",
+        "data": {
+            "e": "depthUpdate", "E": 1, "s": "ETHUSDT",
+            "U": 1, "u": 1, "pu": 0, "b": [], "a": []
+        }
+    }))
+    .unwrap()
+}
+
+/// Polls until `conn` is open (or closed) on `source`.
+async fn wait_open(source: &ChannelSource, conn: Connection, open: bool) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while (source.send(conn, probe()) > 0) != open {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("{conn:?} never became open={open}"));
+}
+
+#[tokio::test]
+async fn handover_from_a_to_b_flushes_parked_updates_without_resync() {
+    // OnlyA now, BothAB from cut_b - 2s, OnlyB from cut_b.
+    let now = Utc::now().time().with_nanosecond(0).unwrap();
+    let config = StreamConfig::builder(["BTCUSDT"])
+        .switch_cutoffs(now - ChronoDur::hours(1), now + ChronoDur::seconds(4))
+        .overlap(Duration::from_secs(2))
+        .build()
+        .unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    // OnlyA
+    assert_eq!(source.send(Connection::B, probe()), 0);
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0"));
+    wait_for_u(&mut book, 102).await;
+
+    // BothAB: A forwards, B parks the same updates.
+    wait_open(&source, Connection::B, true).await;
+    for ev in [
+        futures(103, 105, 102, "97.0", "3.0"),
+        futures(106, 108, 105, "96.0", "4.0"),
+    ] {
+        source.send(Connection::A, ev.clone());
+        source.send(Connection::B, ev);
+    }
+    wait_for_u(&mut book, 108).await;
+    // B is ahead of A at the cutoff.
+    source.send(Connection::B, futures(109, 110, 108, "95.0", "5.0"));
+
+    // OnlyB: the park is flushed; 103..108 are duplicates the book drops.
+    wait_open(&source, Connection::A, false).await;
+    let ob = wait_for_u(&mut book, 110).await;
+    assert_eq!(bid(&ob, 95.0), Some(5.0));
+
+    source.send(Connection::B, futures(111, 112, 110, "94.0", "6.0"));
+    let ob = wait_for_u(&mut book, 112).await;
+    assert_eq!(ob.snapshot_id, Some(100));
+    for (price, qty) in [(98.0, 2.0), (97.0, 3.0), (96.0, 4.0), (95.0, 5.0), (94.0, 6.0)] {
+        assert_eq!(bid(&ob, price), Some(qty), "bid {price}");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let status = handle.feed_status("BTCUSDT").unwrap();
+    assert_eq!(status.borrow().status, FeedStatus::Live);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn spot_partial_payload_is_routed_by_stream_name() {
+    let stream = DepthStream::Partial {
+        levels: 5,
+        speed_ms: Some(100),
+    };
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .market(Market::Spot)
+        .depth_stream(stream)
+        .build()
+        .unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(ChannelSource::new(64));
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    assert_eq!(source.streams(Connection::A), vec![("BTCUSDT".to_string(), stream)]);
+    assert!(source.streams(Connection::B).is_empty());
+
+    // No `s` in a spot partial payload, nor a receive stamp from the source.
+    let env = serde_json::from_value(serde_json::json!({
+        "stream": "btcusdt@depth5@100ms",
+        "data": {
+            "lastUpdateId": 160,
+            "bids": [["98.0", "2.0"]],
+            "asks": [["99.0", "1.0"]]
+        }
+    }))
+    .unwrap();
+    source.send(Connection::A, env);
+
+    let ob = wait_for_u(&mut book, 160).await;
+    assert_eq!(bid(&ob, 98.0), Some(2.0));
+    assert!(ob.last_received.is_some());
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    handle.shutdown().await;
+}
+
+/// Source whose connections can be dropped, made to hang while opening, or
+/// refused.
+#[derive(Default)]
+struct Droppable {
+    a: Mutex<Option<mpsc::Sender<CombinedDepthUpdate>>>,
+    b: Mutex<Option<mpsc::Sender<CombinedDepthUpdate>>>,
+    hang_a: AtomicBool,
+    hang_b: AtomicBool,
+    refuse_a: AtomicBool,
+}
+
+impl Droppable {
+    fn slot(&self, conn: Connection) -> &Mutex<Option<mpsc::Sender<CombinedDepthUpdate>>> {
+        match conn {
+            Connection::A => &self.a,
+            Connection::B => &self.b,
+        }
+    }
+
+    /// Delivers `env` on `conn`, if the router still reads it.
+    async fn send(&self, conn: Connection, env: CombinedDepthUpdate) {
+        let tx = self.slot(conn).lock().unwrap().clone();
+        let _ = tx.expect("connection never opened").send(env).await;
+    }
+
+    fn hang(&self, conn: Connection) -> &AtomicBool {
+        match conn {
+            Connection::A => &self.hang_a,
+            Connection::B => &self.hang_b,
+        }
+    }
+
+    fn drop_connection(&self, conn: Connection) {
+        self.slot(conn).lock().unwrap().take();
+    }
+}
+
+impl DepthSource for Droppable {
+    fn open<'a>(
+        &'a self,
+        conn: Connection,
+        _streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>> {
+        Box::pin(async move {
+            if self.hang(conn).load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            if conn == Connection::A && self.refuse_a.load(Ordering::SeqCst) {
+                return Err(Error::WsHandshake {
+                    status: None,
+                    source: Box::new(tungstenite::Error::ConnectionClosed),
+                });
+            }
+            let (tx, rx) = mpsc::channel(64);
+            *self.slot(conn).lock().unwrap() = Some(tx);
+            Ok(LiveStream::new(ReceiverStream::new(rx)))
+        })
+    }
+}
+
+#[tokio::test]
+async fn hanging_redial_does_not_stall_the_live_connection() {
+    // BothAB for the next hour.
+    let now = Utc::now().time().with_nanosecond(0).unwrap();
+    let config = StreamConfig::builder(["BTCUSDT"])
+        .switch_cutoffs(now - ChronoDur::hours(6), now + ChronoDur::hours(1))
+        .overlap(Duration::from_secs(2 * 3600))
+        .ws_reconnect(RetryPolicy::default().initial_backoff(Duration::from_millis(20)))
+        .build()
+        .unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(Droppable::default());
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut conn_events = handle.connection_events();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0")).await;
+    wait_for_u(&mut book, 102).await;
+
+    // The standby drops and its redial never completes.
+    source.hang(Connection::B).store(true, Ordering::SeqCst);
+    source.drop_connection(Connection::B);
+    let event = tokio::time::timeout(Duration::from_secs(5), conn_events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(event, ConnectionEvent::Disconnected { connection: Connection::B, .. }));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    source.send(Connection::A, futures(103, 105, 102, "97.0", "3.0")).await;
+    let ob = wait_for_u(&mut book, 105).await;
+    assert_eq!(bid(&ob, 97.0), Some(3.0));
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn handover_waits_for_the_incoming_connection() {
+    // BothAB now, OnlyA from cut_a.
+    let now = Utc::now().time().with_nanosecond(0).unwrap();
+    let config = StreamConfig::builder(["BTCUSDT"])
+        .switch_cutoffs(now + ChronoDur::seconds(3), now - ChronoDur::hours(6))
+        .overlap(Duration::from_secs(2 * 3600))
+        .ws_reconnect(RetryPolicy::default().initial_backoff(Duration::from_millis(20)))
+        .build()
+        .unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(Droppable::default());
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+    let mut conn_events = handle.connection_events();
+
+    source.send(Connection::A, futures(96, 102, 95, "98.0", "2.0")).await;
+    wait_for_u(&mut book, 102).await;
+
+    // A drops and B takes over; A can't come back.
+    source.hang(Connection::A).store(true, Ordering::SeqCst);
+    source.drop_connection(Connection::A);
+    let event = tokio::time::timeout(Duration::from_secs(5), conn_events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        matches!(
+            &event,
+            ConnectionEvent::Disconnected { connection: Connection::A, resync } if resync.is_empty()
+        ),
+        "{event:?}"
+    );
+    source.send(Connection::B, futures(103, 105, 102, "97.0", "3.0")).await;
+    wait_for_u(&mut book, 105).await;
+
+    // Past the cutoff B stays up, since A never opened.
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+    source.send(Connection::B, futures(106, 108, 105, "96.0", "4.0")).await;
+    let ob = wait_for_u(&mut book, 108).await;
+    assert_eq!(bid(&ob, 96.0), Some(4.0));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn both_ab_startup_forwards_from_b_when_a_fails_to_open() {
+    // BothAB for the next hour.
+    let now = Utc::now().time().with_nanosecond(0).unwrap();
+    let config = StreamConfig::builder(["BTCUSDT"])
+        .switch_cutoffs(now - ChronoDur::hours(6), now + ChronoDur::hours(1))
+        .overlap(Duration::from_secs(2 * 3600))
+        // No redial of A within the test; startup alone must pick B.
+        .ws_reconnect(
+            RetryPolicy::default()
+                .initial_backoff(Duration::from_secs(3600))
+                .max_backoff(Duration::from_secs(3600)),
+        )
+        .build()
+        .unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(Droppable::default());
+    source.refuse_a.store(true, Ordering::SeqCst);
+    let handle = generate_orderbooks_with(config, provider, source.clone()).await.unwrap();
+    let mut book = handle.order_book("BTCUSDT").unwrap();
+
+    source.send(Connection::B, futures(96, 102, 95, "98.0", "2.0")).await;
+    let ob = wait_for_u(&mut book, 102).await;
+    assert_eq!(bid(&ob, 98.0), Some(2.0));
+    handle.shutdown().await;
+}
+
+/// A source whose connections never open: each attempt fails with a
+/// handshake error carrying `status`.
+struct Unopenable {
+    status: Option<u16>,
+    opens: AtomicUsize,
+}
+
+impl DepthSource for Unopenable {
+    fn open<'a>(
+        &'a self,
+        _conn: Connection,
+        _streams: &'a [(String, DepthStream)],
+    ) -> BoxFuture<'a, Result<LiveStream, Error>> {
+        self.opens.fetch_add(1, Ordering::SeqCst);
+        Box::pin(std::future::ready(Err(Error::WsHandshake {
+            status: self.status,
+            source: Box::new(tungstenite::Error::ConnectionClosed),
+        })))
+    }
+}
+
+#[tokio::test]
+async fn rejected_handshake_fails_startup() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"])).build().unwrap();
+    let (provider, calls) = snapshots(&[100]);
+    let source = Arc::new(Unopenable { status: Some(451), opens: AtomicUsize::new(0) });
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        generate_orderbooks_with(config, provider, source.clone()),
+    )
+    .await
+    .expect("startup hung");
+    assert!(
+        matches!(result, Err(Error::WsHandshake { status: Some(451), .. })),
+        "{:?}",
+        result.err()
+    );
+    assert_eq!(source.opens.load(Ordering::SeqCst), 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn startup_fails_once_ws_reconnect_gives_up() {
+    let config = only_a(StreamConfig::builder(["BTCUSDT"]))
+        .ws_reconnect(
+            RetryPolicy::default()
+                .initial_backoff(Duration::from_millis(10))
+                .max_attempts(Some(2)),
+        )
+        .build()
+        .unwrap();
+    let (provider, _) = snapshots(&[100]);
+    let source = Arc::new(Unopenable { status: None, opens: AtomicUsize::new(0) });
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        generate_orderbooks_with(config, provider, source.clone()),
+    )
+    .await
+    .expect("startup hung");
+    assert!(
+        matches!(result, Err(Error::WsHandshake { status: None, .. })),
+        "{:?}",
+        result.err()
+    );
+    // The first open and both reconnect attempts.
+    assert_eq!(source.opens.load(Ordering::SeqCst), 3);
+}